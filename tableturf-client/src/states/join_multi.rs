@@ -85,7 +85,7 @@ impl GameState for JoinMultiplayer {
 
     fn server_msg(&mut self, msg: ServerMessage, _rl: &mut RaylibHandle, _ctx: &mut GameContext) {
         match msg {
-            ServerMessage::HelloClient => {
+            ServerMessage::HelloClient { .. } => {
                 if let State::WaitingForServer(info) = &mut self.state {
                     self.state = State::Joined(std::mem::take(info));
                 }
@@ -0,0 +1,294 @@
+//! Move timers for matches, so that a player who stops responding can't stall a match forever.
+
+use std::time::Duration;
+
+use tableturf::protocol::PlayerId;
+use tokio::time::Instant;
+
+/// How long players get to make their moves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimerConfig {
+    /// The time a player has to act each turn.
+    pub turn: Duration,
+    /// The time a player has to act over the whole match. Each turn uses up the time the player
+    /// took to act, and once it runs out the player only has what is left of it each turn.
+    pub total: Duration,
+    /// A player who times out this many turns in a row forfeits the match.
+    pub max_timeouts: u32,
+    /// How often players are sent the time they have remaining.
+    pub update_interval: Duration,
+    /// How long a match is paused for when a player's connection drops, to give them a chance to
+    /// reconnect.
+    pub reconnect: Duration,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            turn: Duration::from_secs(60),
+            total: Duration::from_secs(8 * 60),
+            max_timeouts: 3,
+            update_interval: Duration::from_secs(10),
+            reconnect: Duration::from_secs(60),
+        }
+    }
+}
+
+/// What happened to a player who ran out of time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Timeout {
+    /// The server should act on the player's behalf.
+    AutoMove,
+    /// The player has timed out too many times in a row and should forfeit the match.
+    Forfeit,
+}
+
+/// Both players' clocks for a match.
+#[derive(Debug, Clone)]
+pub struct MatchClock {
+    config: TimerConfig,
+    turn_start: Instant,
+    /// The time left on each player's match clock as of the start of the turn.
+    total: [Duration; 2],
+    /// Whether each player has acted this turn, which stops their clock.
+    stopped: [bool; 2],
+    /// The number of turns in a row each player has timed out.
+    timeouts: [u32; 2],
+    /// When the clocks were paused, if they are paused.
+    paused_at: Option<Instant>,
+}
+
+impl MatchClock {
+    /// Creates the clocks and starts the first turn.
+    pub fn new(config: TimerConfig, now: Instant) -> Self {
+        Self {
+            config,
+            turn_start: now,
+            total: [config.total; 2],
+            stopped: [false; 2],
+            timeouts: [0; 2],
+            paused_at: None,
+        }
+    }
+
+    /// Stops both players' clocks until [`MatchClock::resume`] is called.
+    pub fn pause(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    /// Restarts both players' clocks after a pause, as if no time passed while they were paused.
+    pub fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.turn_start += now.saturating_duration_since(paused_at);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Returns how long the current turn has been running for, not counting pauses.
+    fn elapsed(&self, now: Instant) -> Duration {
+        self.paused_at
+            .unwrap_or(now)
+            .saturating_duration_since(self.turn_start)
+    }
+
+    /// Restarts both players' clocks for a new turn.
+    pub fn start_turn(&mut self, now: Instant) {
+        self.turn_start = now;
+        self.stopped = [false; 2];
+        self.paused_at = None;
+    }
+
+    /// Returns the time the player has to act this turn, measured from the start of the turn.
+    pub fn turn_limit(&self, player: PlayerId) -> Duration {
+        self.config.turn.min(self.total[player as usize])
+    }
+
+    /// Returns the time the player has left this turn.
+    pub fn turn_left(&self, player: PlayerId, now: Instant) -> Duration {
+        if self.stopped[player as usize] {
+            return Duration::ZERO;
+        }
+
+        self.turn_limit(player).saturating_sub(self.elapsed(now))
+    }
+
+    /// Returns the time left on the player's clock for the whole match.
+    pub fn total_left(&self, player: PlayerId, now: Instant) -> Duration {
+        let total = self.total[player as usize];
+
+        if self.stopped[player as usize] {
+            return total;
+        }
+
+        total.saturating_sub(self.elapsed(now))
+    }
+
+    /// Returns the earliest time a player who hasn't acted yet will run out of time this turn.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.is_paused() {
+            return None;
+        }
+
+        [PlayerId::P1, PlayerId::P2]
+            .into_iter()
+            .filter(|&p| !self.stopped[p as usize])
+            .map(|p| self.turn_start + self.turn_limit(p))
+            .min()
+    }
+
+    /// Returns the players who haven't acted and have run out of time.
+    pub fn expired(&self, now: Instant) -> Vec<PlayerId> {
+        [PlayerId::P1, PlayerId::P2]
+            .into_iter()
+            .filter(|&p| {
+                !self.is_paused() && !self.stopped[p as usize] && self.turn_left(p, now).is_zero()
+            })
+            .collect()
+    }
+
+    /// Stops the player's clock because they acted in time.
+    pub fn stop(&mut self, player: PlayerId, now: Instant) {
+        let i = player as usize;
+
+        if !self.stopped[i] {
+            self.total[i] = self.total_left(player, now);
+            self.stopped[i] = true;
+            self.timeouts[i] = 0;
+        }
+    }
+
+    /// Stops the player's clock because they ran out of time, returning what should happen to
+    /// them.
+    pub fn time_out(&mut self, player: PlayerId, now: Instant) -> Timeout {
+        let i = player as usize;
+
+        self.total[i] = self.total_left(player, now);
+        self.stopped[i] = true;
+        self.timeouts[i] += 1;
+
+        if self.timeouts[i] >= self.config.max_timeouts {
+            Timeout::Forfeit
+        } else {
+            Timeout::AutoMove
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn config() -> TimerConfig {
+        TimerConfig {
+            turn: 30 * SECOND,
+            total: 45 * SECOND,
+            max_timeouts: 2,
+            update_interval: 5 * SECOND,
+            reconnect: 10 * SECOND,
+        }
+    }
+
+    #[test]
+    fn test_turn_clock() {
+        let start = Instant::now();
+        let mut clock = MatchClock::new(config(), start);
+
+        assert_eq!(clock.next_deadline(), Some(start + 30 * SECOND));
+        assert_eq!(
+            clock.turn_left(PlayerId::P1, start + 10 * SECOND),
+            20 * SECOND
+        );
+        assert_eq!(
+            clock.total_left(PlayerId::P1, start + 10 * SECOND),
+            35 * SECOND
+        );
+        assert!(clock.expired(start + 29 * SECOND).is_empty());
+
+        clock.stop(PlayerId::P1, start + 20 * SECOND);
+        assert_eq!(
+            clock.turn_left(PlayerId::P1, start + 25 * SECOND),
+            Duration::ZERO
+        );
+        assert_eq!(
+            clock.total_left(PlayerId::P1, start + 25 * SECOND),
+            25 * SECOND
+        );
+        assert_eq!(clock.expired(start + 30 * SECOND), vec![PlayerId::P2]);
+
+        assert_eq!(
+            clock.time_out(PlayerId::P2, start + 30 * SECOND),
+            Timeout::AutoMove
+        );
+        assert_eq!(clock.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_match_clock_runs_out() {
+        let start = Instant::now();
+        let mut clock = MatchClock::new(config(), start);
+
+        clock.stop(PlayerId::P1, start + 25 * SECOND);
+        clock.stop(PlayerId::P2, start + SECOND);
+
+        // P1 only has 20 seconds of their match clock left, which is less than a turn
+        let turn = start + 30 * SECOND;
+        clock.start_turn(turn);
+        assert_eq!(clock.turn_limit(PlayerId::P1), 20 * SECOND);
+        assert_eq!(clock.turn_limit(PlayerId::P2), 30 * SECOND);
+        assert_eq!(clock.next_deadline(), Some(turn + 20 * SECOND));
+        assert_eq!(clock.expired(turn + 20 * SECOND), vec![PlayerId::P1]);
+    }
+
+    #[test]
+    fn test_forfeit_after_consecutive_timeouts() {
+        let start = Instant::now();
+        let mut clock = MatchClock::new(config(), start);
+
+        assert_eq!(
+            clock.time_out(PlayerId::P1, start + 30 * SECOND),
+            Timeout::AutoMove
+        );
+
+        // Acting in time resets the count
+        clock.start_turn(start + 30 * SECOND);
+        clock.stop(PlayerId::P1, start + 31 * SECOND);
+        clock.start_turn(start + 32 * SECOND);
+        assert_eq!(
+            clock.time_out(PlayerId::P1, start + 45 * SECOND),
+            Timeout::AutoMove
+        );
+
+        clock.start_turn(start + 46 * SECOND);
+        assert_eq!(
+            clock.time_out(PlayerId::P1, start + 46 * SECOND),
+            Timeout::Forfeit
+        );
+    }
+
+    #[test]
+    fn test_pause() {
+        let start = Instant::now();
+        let mut clock = MatchClock::new(config(), start);
+
+        clock.pause(start + 10 * SECOND);
+        assert!(clock.is_paused());
+        assert_eq!(clock.next_deadline(), None);
+        assert!(clock.expired(start + 100 * SECOND).is_empty());
+        assert_eq!(
+            clock.turn_left(PlayerId::P1, start + 100 * SECOND),
+            20 * SECOND
+        );
+
+        clock.resume(start + 50 * SECOND);
+        assert_eq!(clock.next_deadline(), Some(start + 70 * SECOND));
+        assert_eq!(
+            clock.total_left(PlayerId::P2, start + 55 * SECOND),
+            30 * SECOND
+        );
+    }
+}
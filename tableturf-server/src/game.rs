@@ -1,11 +1,32 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use color_eyre::eyre::OptionExt;
-use tableturf::protocol::{PlayerId, ServerMessage};
-use tokio::sync::{oneshot, mpsc};
-use tracing::{error, info, instrument};
 
-use crate::server::{ClientConnection, ClientId, SharedState};
+use tableturf::{
+    cards::{Card, Deck},
+    game::{Action, Event, GameState, PlayerMove},
+    protocol::{
+        ClientMessage, GameSnapshot, MatchId, MatchSnapshot, PlayerId, PublicGameSnapshot,
+        PublicPlayerInfo, ServerMessage, SessionToken, SpectatorSnapshot, PROTOCOL_VERSION,
+    },
+    replay::ReplayHeader,
+    rng::{random_seed, Rng},
+    stages::StageID,
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{sleep_until, Instant},
+};
+use tracing::{error, info, instrument, warn};
+
+use crate::{
+    clock::{MatchClock, Timeout},
+    record::Recorder,
+    server::{ClientConnection, ClientId, SharedState},
+};
 
 #[derive(Debug)]
 pub enum GameEvent {
@@ -13,6 +34,29 @@ pub enum GameEvent {
     MatchFound(oneshot::Sender<Arc<ClientConnection>>),
     /// Signals to an in-game client that the game is over and it shoudld return to the lobby.
     GameEnded,
+    /// Signals to a client trying to rejoin a match that its seat is still taken by a live
+    /// connection.
+    ResumeRejected,
+}
+
+/// A new connection from a player who wants to rejoin a match after their connection dropped.
+#[derive(Debug)]
+pub struct Resume {
+    pub token: SessionToken,
+    pub connection: Arc<ClientConnection>,
+    /// Where the player's [`GameEvent`]s should be sent from now on.
+    pub events: mpsc::UnboundedSender<GameEvent>,
+}
+
+/// Where to send the public messages of a match to a spectator.
+pub type Spectator = mpsc::UnboundedSender<ServerMessage>;
+
+/// A match in progress, as listed to clients who want to spectate.
+#[derive(Debug)]
+pub struct LiveMatch {
+    pub players: [PublicPlayerInfo; 2],
+    /// Where to send new spectators of the match.
+    pub spectate: mpsc::UnboundedSender<Spectator>,
 }
 
 #[instrument(skip(shared_state))]
@@ -20,17 +64,32 @@ pub async fn handle_game(shared_state: Arc<SharedState>, players: [ClientId; 2])
     let (mut tx1, mut tx2) = {
         let channels = shared_state.channels.lock().await;
 
-        (
-            channels.get(&players[0]).ok_or_eyre("Channel not found").unwrap().clone(),
-            channels.get(&players[1]).ok_or_eyre("Channel not found").unwrap().clone()
-        )
+        match (channels.get(&players[0]), channels.get(&players[1])) {
+            (Some(tx1), Some(tx2)) => (tx1.clone(), tx2.clone()),
+            _ => {
+                // Someone left between being matched and the match starting, so whoever is still
+                // here goes back to looking for a match
+                warn!("A matched player left before the match could start");
+                let remaining: Vec<_> = players.into_iter().filter(|id| channels.contains_key(id)).collect();
+                drop(channels);
+
+                for id in remaining {
+                    shared_state.requeue(id).await;
+                }
+                return;
+            }
+        }
     };
 
-    match handle_game_inner(shared_state, (&mut tx1, &mut tx2), players).await {
+    match handle_game_inner(Arc::clone(&shared_state), (&mut tx1, &mut tx2), players).await {
         Ok(()) => {},
         Err(e) => error!("Game handler task encountered error: {e:?}"),
     }
 
+    // The match can't be rejoined or spectated any more
+    shared_state.resumable.lock().await.retain(|_, tx| !tx.is_closed());
+    shared_state.matches.lock().await.retain(|_, m| !m.spectate.is_closed());
+
     info!("Game handler closing down");
 
     let _ = tx1.send(GameEvent::GameEnded);
@@ -40,7 +99,7 @@ pub async fn handle_game(shared_state: Arc<SharedState>, players: [ClientId; 2])
 async fn handle_game_inner(
     shared_state: Arc<SharedState>,
     (tx1, tx2): (&mut mpsc::UnboundedSender<GameEvent>, &mut mpsc::UnboundedSender<GameEvent>),
-    players: [ClientId; 2]
+    ids: [ClientId; 2]
 ) -> color_eyre::Result<()> {
     let (gtx1, rx1) = oneshot::channel();
     let (gtx2, rx2) = oneshot::channel();
@@ -54,32 +113,567 @@ async fn handle_game_inner(
 
     let (info1, info2) = {
         let infos = shared_state.players.lock().await;
-        (infos.get(&players[0]).unwrap().clone(), infos.get(&players[1]).unwrap().clone())
+        (infos.get(&ids[0]).unwrap().clone(), infos.get(&ids[1]).unwrap().clone())
     };
 
     connection1.send(&ServerMessage::MatchFound { opp_info: info2.clone(), player_id: PlayerId::P1 }).await?;
     connection2.send(&ServerMessage::MatchFound { opp_info: info1.clone(), player_id: PlayerId::P2 }).await?;
 
-    loop {
+    let (resume_tx, resume_rx) = mpsc::unbounded_channel();
+    let tokens = [
+        shared_state.token(ids[0]).await.ok_or_eyre("Session not found")?,
+        shared_state.token(ids[1]).await.ok_or_eyre("Session not found")?,
+    ];
+
+    {
+        let mut resumable = shared_state.resumable.lock().await;
+        for token in &tokens {
+            resumable.insert(token.clone(), resume_tx.clone());
+        }
+    }
+
+    let (spectate_tx, spectate_rx) = mpsc::unbounded_channel();
+    let match_id: MatchId = random_seed();
+    let live = LiveMatch { players: [info1.clone(), info2.clone()], spectate: spectate_tx };
+    shared_state.matches.lock().await.insert(match_id, live);
+
+    let mut players = Players {
+        connections: [connection1, connection2],
+        events: [tx1, tx2],
+        tokens,
+        infos: [info1, info2],
+        disconnected: [None; 2],
+        resume: resume_rx,
+        spectate: spectate_rx,
+        spectators: Vec::new(),
+    };
+    let grace = shared_state.timers.reconnect;
+
+    // Wait for both players to choose a valid deck, which they get a turn's worth of time for
+    let mut decks: [Option<Vec<Card>>; 2] = [None, None];
+    let mut deck_clock = MatchClock::new(shared_state.timers, Instant::now());
+    send_timeouts(&players, &deck_clock).await;
+
+    while decks.iter().any(Option::is_none) {
+        let wake = deck_clock.next_deadline();
+
+        let incoming = tokio::select! {
+            incoming = players.next(grace) => incoming,
+            _ = sleep_until(wake.unwrap_or_else(Instant::now)), if wake.is_some() => {
+                let late = deck_clock.expired(Instant::now());
+
+                for &player in &late {
+                    info!("{player:?} \"{}\" did not choose a deck in time", players.infos[player as usize].name);
+                    players.broadcast(&ServerMessage::TimedOut { player }).await;
+                }
+
+                if late.is_empty() {
+                    continue;
+                }
+
+                return Ok(());
+            }
+        };
+
+        let (player, msg) = match incoming {
+            Incoming::Message(player, msg) => (player, msg),
+            Incoming::Disconnected(player) => {
+                deck_clock.pause(Instant::now());
+                players.announce_disconnected(player, grace).await;
+                continue;
+            }
+            Incoming::Resumed(player) => {
+                if !players.is_paused() {
+                    deck_clock.resume(Instant::now());
+                }
+
+                players.announce_reconnected(player).await;
+
+                let snapshot = players.snapshot(player, decks[player as usize].is_some(), None);
+                players.send(player, &ServerMessage::Resumed { snapshot }).await;
+                continue;
+            }
+            Incoming::Spectator(spectator) => {
+                players.add_spectator(spectator, None);
+                continue;
+            }
+            Incoming::Gone(player) => return players.end_disconnected(player).await,
+        };
+        let i = player as usize;
+
+        match msg {
+            ClientMessage::ChosenDeck { deck } if decks[i].is_none() => {
+                if check_deck(&shared_state, &players.connections[i], &deck).await? {
+                    info!("{player:?} \"{}\" has chosen their deck", players.infos[i].name);
+                    decks[i] = Some(deck_cards(&shared_state, &deck));
+                    deck_clock.stop(player, Instant::now());
+                }
+            }
+            msg => warn!("{player:?} sent message that doesn't align with protocol ({msg:?})."),
+        }
+    }
+
+    let [Some(deck1), Some(deck2)] = decks else {
+        unreachable!()
+    };
+
+    let seed = random_seed();
+    let stage_id = Rng::new(seed).below(shared_state.stages.len());
+    let stage = shared_state.stages.get(stage_id).ok_or_eyre("Stage not found")?;
+
+    let header = ReplayHeader {
+        protocol: PROTOCOL_VERSION,
+        seed,
+        stage: stage_id,
+        board: stage.board().to_string(),
+        decks: [deck1.iter().map(Card::id).collect(), deck2.iter().map(Card::id).collect()],
+        players: [players.infos[0].name.clone(), players.infos[1].name.clone()],
+    };
+    let mut recorder = match &shared_state.replays {
+        Some(dir) => {
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            Recorder::create(dir, &format!("{time}-{match_id:016x}.replay"), &header).await
+        }
+        None => Recorder::disabled(),
+    };
+
+    let mut game = GameState::new(stage.board().clone(), [deck1, deck2], seed);
+
+    info!("Starting match on {:?} with seed {seed}", stage.name());
+
+    let started = ServerMessage::MatchStarted { stage: stage_id, decks: header.decks.clone() };
+
+    for player in [PlayerId::P1, PlayerId::P2] {
+        let hand = game.hand(player).iter().map(Card::id).collect();
+
+        players.send(player, &started).await;
+        players.send(player, &ServerMessage::OpeningHand { hand }).await;
+    }
+
+    players.send_spectators(&started);
+
+    // Play the match
+    let timers = shared_state.timers;
+    let mut clock = MatchClock::new(timers, Instant::now());
+    let mut next_update = Instant::now() + timers.update_interval;
+    send_timeouts(&players, &clock).await;
+
+    while !game.is_finished() {
+        let wake = clock.next_deadline().map_or(next_update, |d| d.min(next_update));
+
         tokio::select! {
-            msg = connection1.next() => {
-                let Some(msg) = msg? else { 
-                    info!("P1 \"{}\" disconnected unexpectedly", info1.name);
-                    connection2.send(&ServerMessage::OpponentDisconnected).await?;
-                    break;
+            incoming = players.next(grace) => {
+                let (player, msg) = match incoming {
+                    Incoming::Message(player, msg) => (player, msg),
+                    Incoming::Disconnected(player) => {
+                        clock.pause(Instant::now());
+                        players.announce_disconnected(player, grace).await;
+                        continue;
+                    }
+                    Incoming::Resumed(player) => {
+                        if !players.is_paused() {
+                            clock.resume(Instant::now());
+                        }
+
+                        players.announce_reconnected(player).await;
+
+                        let game_snapshot = game_snapshot(&game, &clock, stage_id, player);
+                        let snapshot = players.snapshot(player, true, Some(game_snapshot));
+                        players.send(player, &ServerMessage::Resumed { snapshot }).await;
+                        continue;
+                    }
+                    Incoming::Spectator(spectator) => {
+                        players.add_spectator(spectator, Some(public_snapshot(&game, &clock, stage_id)));
+                        continue;
+                    }
+                    Incoming::Gone(player) => {
+                        // Abandoning a match counts as losing it
+                        shared_state.matchmaker.lock().await.record_result(players.names(), Some(player.opponent()));
+                        return players.end_disconnected(player).await;
+                    }
                 };
-                info!("P1 sent event {msg:?}");
-            },
-            msg = connection2.next() => {
-                let Some(msg) = msg? else { 
-                    info!("P2 \"{}\" disconnected unexpectedly", info2.name);
-                    connection1.send(&ServerMessage::OpponentDisconnected).await?;
-                    break;
+
+                let action = match msg {
+                    ClientMessage::Redraw { redraw } => Action::Redraw { player, redraw },
+                    ClientMessage::Move { mv } => Action::Play { player, mv },
+                    msg => {
+                        warn!("{player:?} sent message that doesn't align with protocol ({msg:?}).");
+                        continue;
+                    }
                 };
-                info!("P2 sent event {msg:?}");
+
+                match game.apply(action) {
+                    Ok(events) => {
+                        recorder.record(&action).await;
+                        record_result(&shared_state, players.names(), &events).await;
+                        clock.stop(player, Instant::now());
+                        send_events(&players, &mut clock, events).await;
+                    }
+                    Err(error) => {
+                        warn!("{player:?} made an invalid move: {error}");
+                        players.send(player, &ServerMessage::MoveRejected { error }).await;
+                    }
+                }
+            },
+
+            _ = sleep_until(wake) => {
+                let now = Instant::now();
+
+                for player in clock.expired(now) {
+                    if game.is_finished() {
+                        break;
+                    }
+
+                    let name = &players.infos[player as usize].name;
+                    let action = match clock.time_out(player, now) {
+                        Timeout::Forfeit => {
+                            info!("{player:?} \"{name}\" timed out too many times and forfeits");
+                            Action::Forfeit { player }
+                        }
+                        Timeout::AutoMove => {
+                            info!("{player:?} \"{name}\" timed out");
+                            players.broadcast(&ServerMessage::TimedOut { player }).await;
+                            timeout_action(&game, player)
+                        }
+                    };
+
+                    let events = game.apply(action)?;
+                    recorder.record(&action).await;
+                    record_result(&shared_state, players.names(), &events).await;
+                    send_events(&players, &mut clock, events).await;
+                }
+
+                if now >= next_update {
+                    for player in [PlayerId::P1, PlayerId::P2] {
+                        let msg = ServerMessage::TimeRemaining {
+                            turn: clock.turn_left(player, now).as_secs() as u32,
+                            total: clock.total_left(player, now).as_secs() as u32,
+                        };
+                        players.send(player, &msg).await;
+                    }
+
+                    players.send_spectators(&times_remaining(&clock, now));
+                    next_update = now + timers.update_interval;
+                }
             },
         }
     }
 
+    info!("Match is over, final scores {:?}", game.scores());
+
     Ok(())
 }
+
+/// Something that happened to one of the players in a match.
+enum Incoming {
+    Message(PlayerId, ClientMessage),
+    /// The player's connection dropped. The match is paused while they have a chance to
+    /// reconnect, and their opponent needs to be told.
+    Disconnected(PlayerId),
+    /// The player has rejoined on a new connection, and needs to be sent the state of the match.
+    /// Their opponent needs to be told too.
+    Resumed(PlayerId),
+    /// The player didn't reconnect in time.
+    Gone(PlayerId),
+    /// Someone wants to spectate the match, and needs to be sent its state.
+    Spectator(Spectator),
+}
+
+/// The players in a match and their connections, which can be replaced if a player reconnects.
+struct Players<'a> {
+    connections: [Arc<ClientConnection>; 2],
+    events: [&'a mut mpsc::UnboundedSender<GameEvent>; 2],
+    tokens: [SessionToken; 2],
+    infos: [PublicPlayerInfo; 2],
+    /// For each player whose connection has dropped, when they must have reconnected by.
+    disconnected: [Option<Instant>; 2],
+    resume: mpsc::UnboundedReceiver<Resume>,
+    spectate: mpsc::UnboundedReceiver<Spectator>,
+    /// Spectators are sent messages over unbounded channels and never awaited, so that a slow
+    /// spectator can't hold up the match.
+    spectators: Vec<Spectator>,
+}
+
+impl Players<'_> {
+    /// Waits for something to happen to either player. If a player disconnects, they get `grace`
+    /// to reconnect before they are considered gone.
+    ///
+    /// This is cancel safe: nothing is awaited after a player's state changes, so the match can
+    /// race it against its timers. Telling anyone else about the change is up to the caller.
+    async fn next(&mut self, grace: std::time::Duration) -> Incoming {
+        loop {
+            let deadline = self.disconnected.iter().flatten().min().copied();
+
+            let (player, msg) = tokio::select! {
+                msg = self.connections[0].next(), if self.disconnected[0].is_none() => (PlayerId::P1, msg),
+                msg = self.connections[1].next(), if self.disconnected[1].is_none() => (PlayerId::P2, msg),
+                Some(resume) = self.resume.recv() => {
+                    let Some(player) = self.replace(resume) else {
+                        continue;
+                    };
+
+                    return Incoming::Resumed(player);
+                }
+                Some(spectator) = self.spectate.recv() => return Incoming::Spectator(spectator),
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let gone = (0..2).find(|&i| self.disconnected[i] == deadline).unwrap();
+                    return Incoming::Gone([PlayerId::P1, PlayerId::P2][gone]);
+                }
+            };
+
+            let i = player as usize;
+
+            match msg {
+                Ok(Some(msg)) => return Incoming::Message(player, msg),
+                Ok(None) => info!("{player:?} \"{}\" disconnected", self.infos[i].name),
+                Err(e) => warn!("Connection to {player:?} \"{}\" failed: {e:?}", self.infos[i].name),
+            }
+
+            self.disconnected[i] = Some(Instant::now() + grace);
+            return Incoming::Disconnected(player);
+        }
+    }
+
+    /// Swaps in the new connection of a player who has reconnected, returning which player it
+    /// was. The new connection is turned away if the player's old one hasn't dropped.
+    fn replace(&mut self, resume: Resume) -> Option<PlayerId> {
+        let i = self.tokens.iter().position(|t| *t == resume.token)?;
+        let player = [PlayerId::P1, PlayerId::P2][i];
+
+        if self.disconnected[i].is_none() {
+            warn!("{player:?} \"{}\" tried to rejoin while still connected", self.infos[i].name);
+            let _ = resume.events.send(GameEvent::ResumeRejected);
+            return None;
+        }
+
+        info!("{player:?} \"{}\" has reconnected", self.infos[i].name);
+
+        self.connections[i] = resume.connection;
+        self.disconnected[i] = None;
+
+        // The task that handled the old connection is still waiting for the match to end
+        let old = std::mem::replace(&mut *self.events[i], resume.events);
+        let _ = old.send(GameEvent::GameEnded);
+
+        Some(player)
+    }
+
+    /// Tells the player's opponent and the spectators that the player's connection has dropped.
+    async fn announce_disconnected(&self, player: PlayerId, grace: std::time::Duration) {
+        let timeout = grace.as_secs() as u32;
+
+        self.send(player.opponent(), &ServerMessage::OpponentReconnecting { timeout }).await;
+        self.send_spectators(&ServerMessage::PlayerReconnecting { player, timeout });
+    }
+
+    /// Tells the player's opponent and the spectators that the player has reconnected.
+    async fn announce_reconnected(&self, player: PlayerId) {
+        self.send(player.opponent(), &ServerMessage::OpponentReconnected).await;
+        self.send_spectators(&ServerMessage::PlayerReconnected { player });
+    }
+
+    /// Returns whether the match is paused because a player is disconnected.
+    fn is_paused(&self) -> bool {
+        self.disconnected.iter().any(Option::is_some)
+    }
+
+    fn snapshot(&self, player: PlayerId, deck_chosen: bool, game: Option<GameSnapshot>) -> MatchSnapshot {
+        MatchSnapshot {
+            player_id: player,
+            opp_info: self.infos[player.opponent() as usize].clone(),
+            deck_chosen,
+            game,
+        }
+    }
+
+    /// Sends a message to the player. If the player has disconnected, the message is dropped; they
+    /// will be sent the whole state of the match if they reconnect.
+    async fn send(&self, player: PlayerId, msg: &ServerMessage) {
+        let i = player as usize;
+
+        if self.disconnected[i].is_some() {
+            return;
+        }
+
+        if let Err(e) = self.connections[i].send(msg).await {
+            // The next read from the connection will notice that it has dropped
+            warn!("Couldn't send message to {player:?}: {e:?}");
+        }
+    }
+
+    /// Sends a message to both players and all of the spectators. It must not reveal any hidden
+    /// information.
+    async fn broadcast(&self, msg: &ServerMessage) {
+        for player in [PlayerId::P1, PlayerId::P2] {
+            self.send(player, msg).await;
+        }
+
+        self.send_spectators(msg);
+    }
+
+    /// Sends a message to all of the spectators. It must not reveal any hidden information.
+    fn send_spectators(&self, msg: &ServerMessage) {
+        for spectator in &self.spectators {
+            // Spectators that have stopped watching are removed when the next one joins
+            let _ = spectator.send(msg.clone());
+        }
+    }
+
+    /// Starts sending the match to a new spectator.
+    fn add_spectator(&mut self, spectator: Spectator, game: Option<PublicGameSnapshot>) {
+        let snapshot = SpectatorSnapshot { players: self.infos.clone(), game };
+
+        if spectator.send(ServerMessage::Spectating { snapshot }).is_ok() {
+            self.spectators.retain(|s| !s.is_closed());
+            self.spectators.push(spectator);
+        }
+    }
+
+    /// Returns both players' names, which their ratings are kept under.
+    fn names(&self) -> [&str; 2] {
+        [&self.infos[0].name, &self.infos[1].name]
+    }
+
+    /// Ends the match because a player didn't reconnect in time.
+    async fn end_disconnected(&self, player: PlayerId) -> color_eyre::Result<()> {
+        info!("{player:?} \"{}\" did not reconnect in time", self.infos[player as usize].name);
+
+        self.send(player.opponent(), &ServerMessage::OpponentDisconnected).await;
+        self.send_spectators(&ServerMessage::PlayerDisconnected { player });
+        Ok(())
+    }
+}
+
+/// Returns the state of the game as the player sees it.
+fn game_snapshot(game: &GameState, clock: &MatchClock, stage: StageID, player: PlayerId) -> GameSnapshot {
+    GameSnapshot {
+        stage,
+        board: game.board().to_string(),
+        turn: game.turn(),
+        started: game.has_started(),
+        hand: game.hand(player).iter().map(Card::id).collect(),
+        draw_pile_len: game.draw_pile_len(player),
+        special_points: [game.special_points(PlayerId::P1), game.special_points(PlayerId::P2)],
+        acted: [!game.is_waiting_for(PlayerId::P1), !game.is_waiting_for(PlayerId::P2)],
+        timeout: clock.turn_left(player, Instant::now()).as_secs() as u32,
+    }
+}
+
+/// Returns the state of the game that everyone can see.
+fn public_snapshot(game: &GameState, clock: &MatchClock, stage: StageID) -> PublicGameSnapshot {
+    PublicGameSnapshot {
+        stage,
+        board: game.board().to_string(),
+        turn: game.turn(),
+        started: game.has_started(),
+        scores: game.scores(),
+        special_points: [game.special_points(PlayerId::P1), game.special_points(PlayerId::P2)],
+        acted: [!game.is_waiting_for(PlayerId::P1), !game.is_waiting_for(PlayerId::P2)],
+        timeouts: [PlayerId::P1, PlayerId::P2].map(|p| clock.turn_left(p, Instant::now()).as_secs() as u32),
+    }
+}
+
+/// The time both players have left, for the spectators.
+fn times_remaining(clock: &MatchClock, now: Instant) -> ServerMessage {
+    let players = [PlayerId::P1, PlayerId::P2];
+
+    ServerMessage::TimesRemaining {
+        turn: players.map(|p| clock.turn_left(p, now).as_secs() as u32),
+        total: players.map(|p| clock.total_left(p, now).as_secs() as u32),
+    }
+}
+
+/// The action taken on behalf of a player who ran out of time: they keep their opening hand, or
+/// pass with the first card in their hand.
+fn timeout_action(game: &GameState, player: PlayerId) -> Action {
+    if !game.has_started() {
+        return Action::Redraw { player, redraw: false };
+    }
+
+    let card = game.hand(player)[0].id();
+    Action::Play { player, mv: PlayerMove::Pass { card } }
+}
+
+/// Tells each player (and the spectators) how long they have to act this turn.
+async fn send_timeouts(players: &Players<'_>, clock: &MatchClock) {
+    for player in [PlayerId::P1, PlayerId::P2] {
+        let timeout = clock.turn_limit(player).as_secs() as u32;
+        players.send(player, &ServerMessage::StartWithTimeout { timeout }).await;
+    }
+
+    players.send_spectators(&times_remaining(clock, Instant::now()));
+}
+
+/// Sends the events from the game state to the players they concern, restarting the clock when a
+/// new turn starts. Events that reveal hidden information (such as which card was drawn) are only
+/// sent to the player they belong to.
+async fn send_events(players: &Players<'_>, clock: &mut MatchClock, events: Vec<Event>) {
+    for event in events {
+        match event {
+            Event::HandDealt { player, hand } => {
+                players.send(player, &ServerMessage::HandRedrawn { hand }).await;
+            }
+            Event::TurnStarted { turn } => {
+                clock.start_turn(Instant::now());
+                if players.is_paused() {
+                    clock.pause(Instant::now());
+                }
+
+                players.broadcast(&ServerMessage::TurnStarted { turn }).await;
+                send_timeouts(players, clock).await;
+            }
+            Event::MoveLocked { player } => {
+                players.send(player, &ServerMessage::MoveAccepted).await;
+                players.send(player.opponent(), &ServerMessage::OpponentMoved).await;
+                players.send_spectators(&ServerMessage::PlayerMoved { player });
+            }
+            Event::TurnResolved { turn, moves, outcome } => {
+                players.broadcast(&ServerMessage::MovesRevealed { turn, moves }).await;
+                players.broadcast(&ServerMessage::TurnResult { turn, outcome }).await;
+            }
+            Event::CardDrawn { player, card } => {
+                players.send(player, &ServerMessage::CardDrawn { card }).await;
+            }
+            Event::Forfeited { player } => {
+                players.broadcast(&ServerMessage::Forfeited { player }).await;
+            }
+            Event::GameOver { scores, winner } => {
+                players.broadcast(&ServerMessage::GameOver { scores, winner }).await;
+            }
+        }
+    }
+}
+
+/// Updates the players' ratings if the events end the match.
+async fn record_result(shared_state: &SharedState, names: [&str; 2], events: &[Event]) {
+    for event in events {
+        if let Event::GameOver { winner, .. } = event {
+            shared_state.matchmaker.lock().await.record_result(names, *winner);
+        }
+    }
+}
+
+/// Looks up the cards in a deck that has already been validated.
+fn deck_cards(shared_state: &SharedState, deck: &Deck) -> Vec<Card> {
+    deck.cards()
+        .iter()
+        .filter_map(|&id| shared_state.cards.get(id).cloned())
+        .collect()
+}
+
+/// Checks that a deck chosen by a player is allowed, telling the player if it isn't. Returns
+/// whether the deck is valid.
+async fn check_deck(
+    shared_state: &SharedState,
+    connection: &ClientConnection,
+    deck: &Deck,
+) -> color_eyre::Result<bool> {
+    match deck.validate(&shared_state.cards) {
+        Ok(()) => Ok(true),
+        Err(error) => {
+            warn!("Player chose an invalid deck: {error}");
+            connection.send(&ServerMessage::InvalidDeck { error }).await?;
+            Ok(false)
+        }
+    }
+}
@@ -1,4 +1,8 @@
+mod clock;
 mod game;
+mod matchmaking;
+mod record;
 mod server;
 
+pub use clock::TimerConfig;
 pub use server::run;
@@ -1,13 +1,74 @@
-use tableturf_server::run;
+use std::{env, str::FromStr, time::Duration};
+
+use color_eyre::eyre::{eyre, Context};
+use tableturf_server::{run, TimerConfig};
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
     tracing_subscriber::fmt().init();
 
-    let address = std::env::args()
-        .nth(1)
-        .unwrap_or("127.0.0.1:2611".to_owned());
+    let address = env::args().nth(1).unwrap_or("127.0.0.1:2611".to_owned());
+
+    run(&address, timers()?).await
+}
+
+/// Reads the move timers from the environment, using the defaults for any that aren't set.
+///
+/// - `TABLETURF_TURN_SECS`: how long a player has to act each turn.
+/// - `TABLETURF_TOTAL_SECS`: how long a player has to act over the whole match.
+/// - `TABLETURF_MAX_TIMEOUTS`: how many turns in a row a player can time out before they forfeit.
+/// - `TABLETURF_RECONNECT_SECS`: how long a player whose connection drops has to reconnect.
+/// - `TABLETURF_UPDATE_SECS`: how often players are sent the time they have left.
+fn timers() -> color_eyre::Result<TimerConfig> {
+    let mut timers = TimerConfig::default();
+
+    if let Some(turn) = number("TABLETURF_TURN_SECS")? {
+        timers.turn = Duration::from_secs(nonzero("TABLETURF_TURN_SECS", turn)?);
+    }
+
+    if let Some(total) = number("TABLETURF_TOTAL_SECS")? {
+        timers.total = Duration::from_secs(nonzero("TABLETURF_TOTAL_SECS", total)?);
+    }
+
+    if let Some(max_timeouts) = number("TABLETURF_MAX_TIMEOUTS")? {
+        timers.max_timeouts = nonzero("TABLETURF_MAX_TIMEOUTS", max_timeouts)?;
+    }
+
+    if let Some(reconnect) = number("TABLETURF_RECONNECT_SECS")? {
+        timers.reconnect = Duration::from_secs(reconnect);
+    }
+
+    if let Some(update) = number("TABLETURF_UPDATE_SECS")? {
+        timers.update_interval = Duration::from_secs(nonzero("TABLETURF_UPDATE_SECS", update)?);
+    }
+
+    Ok(timers)
+}
+
+/// Reads a whole number from an environment variable, if it is set.
+fn number<T>(var: &str) -> color_eyre::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(value) = env::var_os(var) else {
+        return Ok(None);
+    };
+
+    let value = value.to_string_lossy();
+    let number = value
+        .trim()
+        .parse()
+        .wrap_err(format!("{var} should be a whole number, not {value:?}"))?;
+
+    Ok(Some(number))
+}
 
-    run(&address).await
+fn nonzero<T: Default + PartialEq>(var: &str, number: T) -> color_eyre::Result<T> {
+    if number == T::default() {
+        Err(eyre!("{var} should be at least 1"))
+    } else {
+        Ok(number)
+    }
 }
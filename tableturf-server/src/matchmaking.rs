@@ -0,0 +1,317 @@
+//! Pairing up players who are looking for a match.
+
+use std::{collections::HashMap, time::Duration};
+
+use tableturf::protocol::PlayerId;
+use tokio::time::Instant;
+
+use crate::server::ClientId;
+
+/// The rating a player starts with once they finish their first match.
+pub const INITIAL_RATING: u32 = 1000;
+
+/// How many rating points a single match can move a player's rating by at most.
+const RATING_K: f64 = 32.0;
+
+/// Settings for how players are paired up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MatchmakingConfig {
+    /// How far apart two players' ratings can be for them to be matched when they first join the
+    /// queue.
+    pub rating_window: u32,
+    /// How much the rating window grows for every second a player waits, so that nobody waits
+    /// forever.
+    pub window_growth: u32,
+    /// How long two players who just played each other have to wait before they can be matched
+    /// again, in case someone else comes along.
+    pub rematch_delay: Duration,
+}
+
+impl Default for MatchmakingConfig {
+    fn default() -> Self {
+        Self {
+            rating_window: 100,
+            window_growth: 10,
+            rematch_delay: Duration::from_secs(15),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Ticket {
+    id: ClientId,
+    rating: Option<u32>,
+    joined: Instant,
+}
+
+impl Ticket {
+    /// The furthest away an opponent's rating can be from this player's.
+    fn window(&self, config: &MatchmakingConfig, now: Instant) -> u32 {
+        let waited = now.saturating_duration_since(self.joined).as_secs() as u32;
+        config
+            .rating_window
+            .saturating_add(config.window_growth.saturating_mul(waited))
+    }
+
+    fn waited(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.joined)
+    }
+}
+
+/// A first in, first out queue of players waiting for a match.
+///
+/// Players are matched with the player who has been waiting the longest, as long as:
+///
+/// - they didn't just play each other, unless they have both waited for the rematch delay, and
+/// - if both players have a rating, their ratings are within the rating window of whichever of
+///   them has waited longer.
+///
+/// Players are rated with the Elo system once they finish a match, see
+/// [`Matchmaker::record_result`].
+#[derive(Debug, Default)]
+pub struct Matchmaker {
+    config: MatchmakingConfig,
+    queue: Vec<Ticket>,
+    /// The last opponent of every player who has been matched.
+    last_opponent: HashMap<ClientId, ClientId>,
+    /// The rating of every player who has finished a match, by name. Ratings are kept after
+    /// players leave, so that they carry over to the next time they connect.
+    ratings: HashMap<String, u32>,
+}
+
+impl Matchmaker {
+    pub fn new(config: MatchmakingConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the player to the queue, returning a match if there is already a suitable opponent
+    /// waiting. The player who was waiting comes first.
+    ///
+    /// Does nothing if the player is already in the queue.
+    pub fn join(
+        &mut self,
+        id: ClientId,
+        rating: Option<u32>,
+        now: Instant,
+    ) -> Option<[ClientId; 2]> {
+        if self.contains(id) {
+            return None;
+        }
+
+        self.queue.push(Ticket {
+            id,
+            rating,
+            joined: now,
+        });
+
+        let i = self.queue.len() - 1;
+        let j = (0..i).find(|&j| self.compatible(&self.queue[j], &self.queue[i], now))?;
+
+        Some(self.pair(j, i))
+    }
+
+    /// Removes the player from the queue, returning whether they were in it. If they weren't,
+    /// they may have already been matched.
+    pub fn cancel(&mut self, id: ClientId) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|t| t.id != id);
+        self.queue.len() != len
+    }
+
+    /// Matches up as many waiting players as possible. This should be called every now and then,
+    /// since players who couldn't be matched when they joined may be compatible after waiting.
+    pub fn find_matches(&mut self, now: Instant) -> Vec<[ClientId; 2]> {
+        let mut matches = Vec::new();
+        let mut i = 0;
+
+        while i < self.queue.len() {
+            let found = (i + 1..self.queue.len())
+                .find(|&j| self.compatible(&self.queue[i], &self.queue[j], now));
+
+            match found {
+                Some(j) => matches.push(self.pair(i, j)),
+                None => i += 1,
+            }
+        }
+
+        matches
+    }
+
+    pub fn contains(&self, id: ClientId) -> bool {
+        self.queue.iter().any(|t| t.id == id)
+    }
+
+    /// Returns the player's rating, or None if they haven't finished a match yet.
+    pub fn rating(&self, name: &str) -> Option<u32> {
+        self.ratings.get(name).copied()
+    }
+
+    /// Updates both players' ratings after they finish a match against each other. `winner` is
+    /// None if the match was a draw.
+    pub fn record_result(&mut self, players: [&str; 2], winner: Option<PlayerId>) {
+        let [a, b] = players.map(|name| f64::from(self.rating(name).unwrap_or(INITIAL_RATING)));
+        let expected = 1.0 / (1.0 + 10f64.powf((b - a) / 400.0));
+        let score = match winner {
+            Some(PlayerId::P1) => 1.0,
+            Some(PlayerId::P2) => 0.0,
+            None => 0.5,
+        };
+        let change = RATING_K * (score - expected);
+
+        self.ratings.insert(players[0].to_owned(), (a + change).round().max(0.0) as u32);
+        self.ratings.insert(players[1].to_owned(), (b - change).round().max(0.0) as u32);
+    }
+
+    /// Forgets the player's last opponent, once they have left the server. Their rating is kept.
+    pub fn forget(&mut self, id: ClientId) {
+        self.cancel(id);
+        self.last_opponent.remove(&id);
+    }
+
+    fn compatible(&self, a: &Ticket, b: &Ticket, now: Instant) -> bool {
+        let rematch = self.last_opponent.get(&a.id) == Some(&b.id)
+            || self.last_opponent.get(&b.id) == Some(&a.id);
+
+        if rematch
+            && (a.waited(now) < self.config.rematch_delay
+                || b.waited(now) < self.config.rematch_delay)
+        {
+            return false;
+        }
+
+        match (a.rating, b.rating) {
+            (Some(ra), Some(rb)) => {
+                let window = a.window(&self.config, now).max(b.window(&self.config, now));
+                ra.abs_diff(rb) <= window
+            }
+            _ => true,
+        }
+    }
+
+    /// Takes the players at the given positions (`i < j`) out of the queue as a match.
+    fn pair(&mut self, i: usize, j: usize) -> [ClientId; 2] {
+        let b = self.queue.remove(j).id;
+        let a = self.queue.remove(i).id;
+
+        self.last_opponent.insert(a, b);
+        self.last_opponent.insert(b, a);
+
+        [a, b]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn matchmaker() -> Matchmaker {
+        Matchmaker::new(MatchmakingConfig {
+            rating_window: 50,
+            window_growth: 10,
+            rematch_delay: 5 * SECOND,
+        })
+    }
+
+    fn ids<const N: usize>() -> [ClientId; N] {
+        std::array::from_fn(|_| ClientId::new())
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut mm = matchmaker();
+        let [a, b, c, d] = ids();
+        let now = Instant::now();
+
+        assert_eq!(mm.join(a, None, now), None);
+        assert_eq!(mm.join(b, None, now), Some([a, b]));
+        assert!(!mm.contains(a) && !mm.contains(b));
+
+        // Joining twice doesn't let a player play themselves
+        assert_eq!(mm.join(c, None, now), None);
+        assert_eq!(mm.join(c, None, now), None);
+        assert_eq!(mm.join(d, None, now), Some([c, d]));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut mm = matchmaker();
+        let [a, b] = ids();
+        let now = Instant::now();
+
+        mm.join(a, None, now);
+        assert!(mm.cancel(a));
+        assert!(!mm.cancel(a));
+        assert_eq!(mm.join(b, None, now), None);
+        assert!(mm.contains(b));
+    }
+
+    #[test]
+    fn test_no_back_to_back_rematch() {
+        let mut mm = matchmaker();
+        let [a, b, c] = ids();
+        let now = Instant::now();
+
+        mm.join(a, None, now);
+        mm.join(b, None, now);
+
+        // a and b finish their match and queue again straight away
+        assert_eq!(mm.join(a, None, now), None);
+        assert_eq!(mm.join(b, None, now), None);
+        assert_eq!(mm.join(c, None, now), Some([a, c]));
+
+        // b is left waiting, and can play a again once the delay has passed
+        mm.join(a, None, now + SECOND);
+        assert!(mm.find_matches(now + 5 * SECOND).is_empty());
+        assert_eq!(mm.find_matches(now + 6 * SECOND), vec![[b, a]]);
+    }
+
+    #[test]
+    fn test_rating_window_widens() {
+        let mut mm = matchmaker();
+        let [a, b, c] = ids();
+        let now = Instant::now();
+
+        assert_eq!(mm.join(a, Some(1000), now), None);
+        assert_eq!(mm.join(b, Some(1200), now), None);
+
+        // Players without a rating can play anyone
+        assert_eq!(mm.join(c, None, now), Some([a, c]));
+
+        mm.join(a, Some(1000), now);
+        assert!(mm.find_matches(now + 14 * SECOND).is_empty());
+        assert_eq!(mm.find_matches(now + 15 * SECOND), vec![[b, a]]);
+    }
+
+    #[test]
+    fn test_record_result() {
+        let mut mm = matchmaker();
+        let [a, b, c, d, e] = ["a", "b", "c", "d", "e"];
+
+        assert_eq!(mm.rating(a), None);
+
+        mm.record_result([a, b], Some(PlayerId::P1));
+        assert_eq!(mm.rating(a), Some(1016));
+        assert_eq!(mm.rating(b), Some(984));
+
+        // Beating a stronger player is worth more
+        mm.record_result([c, a], Some(PlayerId::P1));
+        assert_eq!(mm.rating(c), Some(1017));
+        assert_eq!(mm.rating(a), Some(999));
+
+        // A draw between equal players changes nothing
+        mm.record_result([d, e], None);
+        assert_eq!(mm.rating(d), Some(1000));
+        assert_eq!(mm.rating(e), Some(1000));
+
+        // Ratings outlive the connection
+        let id = ClientId::new();
+        assert_eq!(mm.join(id, mm.rating(a), Instant::now()), None);
+        mm.forget(id);
+        assert_eq!(mm.rating(a), Some(999));
+    }
+}
@@ -0,0 +1,119 @@
+//! Recording matches to replay files (see [`tableturf::replay`]) as they are played.
+
+use std::path::{Path, PathBuf};
+
+use tableturf::{
+    game::Action,
+    replay::{action_line, ReplayHeader},
+};
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::{info, warn};
+
+/// The directory replays are saved to by default, relative to where the server is run.
+pub const REPLAY_DIR: &str = "replays";
+
+/// Writes a match's replay file one action at a time, so that it is complete up to the last
+/// action even if the server goes down.
+///
+/// Recording is best effort: if the file can't be written, the error is logged and the match
+/// carries on without being recorded.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Option<fs::File>,
+}
+
+impl Recorder {
+    /// Creates the replay file `name` in `dir` and writes the header to it.
+    pub async fn create(dir: &Path, name: &str, header: &ReplayHeader) -> Self {
+        let path = dir.join(name);
+
+        let file = async {
+            fs::create_dir_all(dir).await?;
+            let mut file = fs::File::create(&path).await?;
+            file.write_all(header.to_line().as_bytes()).await?;
+            Ok::<_, std::io::Error>(file)
+        }
+        .await;
+
+        let file = match file {
+            Ok(file) => {
+                info!("Recording match to {path:?}");
+                Some(file)
+            }
+            Err(e) => {
+                warn!("Couldn't create replay file {path:?}: {e}");
+                None
+            }
+        };
+
+        Self { path, file }
+    }
+
+    /// A recorder that doesn't write anything, for when recording is turned off.
+    pub fn disabled() -> Self {
+        Self {
+            path: PathBuf::new(),
+            file: None,
+        }
+    }
+
+    /// Appends an action that was applied to the match.
+    pub async fn record(&mut self, action: &Action) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        let result = async {
+            file.write_all(action_line(action).as_bytes()).await?;
+            file.flush().await
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!(
+                "Couldn't write to replay file {:?}, no longer recording: {e}",
+                self.path
+            );
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tableturf::{
+        protocol::{PlayerId, PROTOCOL_VERSION},
+        replay::Replay,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recorder_writes_replay() {
+        let dir = std::env::temp_dir().join(format!("tableturf-replays-{}", std::process::id()));
+        let header = ReplayHeader {
+            protocol: PROTOCOL_VERSION,
+            seed: 7,
+            stage: 0,
+            board: "name: Tiny\na.b\n".to_string(),
+            decks: [vec![1, 2], vec![3, 4]],
+            players: ["villuna".to_string(), "callie".to_string()],
+        };
+        let action = Action::Redraw {
+            player: PlayerId::P2,
+            redraw: true,
+        };
+
+        let mut recorder = Recorder::create(&dir, "test.replay", &header).await;
+        recorder.record(&action).await;
+        drop(recorder);
+
+        let file = fs::read_to_string(dir.join("test.replay")).await.unwrap();
+        let replay = Replay::parse(&file).unwrap();
+        assert_eq!(replay.header, header);
+        assert_eq!(replay.actions, vec![action]);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -1,46 +1,104 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, fmt, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use color_eyre::eyre::{eyre, Context};
 use futures::{SinkExt, StreamExt};
-use tableturf::protocol::{ClientMessage, PublicPlayerInfo, ServerMessage};
+use tableturf::{
+    cards::CardCatalog,
+    protocol::{
+        ClientMessage, MatchId, MatchSummary, PublicPlayerInfo, RoomCode, ServerMessage, SessionToken,
+    },
+    rng::{random_seed, Rng},
+    stages::StageCatalog,
+};
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::{
-        mpsc::{unbounded_channel, UnboundedSender},
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
         Mutex,
     },
+    time::{timeout_at, Instant},
 };
 use tokio_util::codec::{Framed, LinesCodec};
 use tracing::{error, info, instrument, warn};
 
-use crate::game::{handle_game, GameEvent};
+use crate::{
+    clock::TimerConfig,
+    game::{handle_game, GameEvent, LiveMatch, Resume},
+    matchmaking::{Matchmaker, MatchmakingConfig},
+    record::REPLAY_DIR,
+};
+
+/// How long a client can go without sending anything before we assume it is dead and disconnect
+/// it. Clients are expected to send [`ClientMessage::Ping`] more often than this.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often the matchmaking queue is checked for players who can now be matched.
+const MATCHMAKING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The characters room codes are made of. Letters and numbers that are easy to mix up (like O
+/// and 0) are left out so that codes can be read out loud.
+const ROOM_CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const ROOM_CODE_LEN: usize = 6;
 
 /// Struct that wraps a connection to a client and handles transforming messages to/from json
 #[derive(Debug)]
 pub struct ClientConnection {
     inner: Mutex<Framed<TcpStream, LinesCodec>>,
+    idle_timeout: Duration,
+    /// When we last received anything from the client.
+    last_seen: std::sync::Mutex<Instant>,
 }
 
 impl ClientConnection {
     /// Create a new client connection.
     pub fn new(socket: TcpStream) -> Self {
+        Self::with_idle_timeout(socket, IDLE_TIMEOUT)
+    }
+
+    /// Create a new client connection that is considered dead if the client goes silent for
+    /// `idle_timeout`.
+    pub fn with_idle_timeout(socket: TcpStream, idle_timeout: Duration) -> Self {
         Self {
             inner: Mutex::new(Framed::new(socket, LinesCodec::new())),
+            idle_timeout,
+            last_seen: std::sync::Mutex::new(Instant::now()),
         }
     }
 
     /// Recieve a message from the connected client. If the client disconnected, this will return
     /// Ok(None). If there was some unexpected error, will return an Err variant.
+    ///
+    /// Pings are answered here, whatever state the client is in, so they are never returned. So
+    /// are lines that aren't valid messages, which get a [`ServerMessage::InvalidMessage`] reply
+    /// and are otherwise skipped. If the client hasn't sent anything for too long, it is treated
+    /// as if it disconnected.
     pub async fn next(&self) -> color_eyre::Result<Option<ClientMessage>> {
-        let line = self.inner.lock().await.next().await;
-
-        match line {
-            None => Ok(None),
-            Some(line) => {
-                let line = line?;
-                let msg = serde_json::from_str(&line)?;
-                Ok(msg)
-            }
+        let mut inner = self.inner.lock().await;
+
+        loop {
+            let deadline = *self.last_seen.lock().unwrap() + self.idle_timeout;
+
+            let Ok(line) = timeout_at(deadline, inner.next()).await else {
+                warn!("Client has gone silent, treating it as disconnected");
+                return Ok(None);
+            };
+
+            let Some(line) = line else {
+                return Ok(None);
+            };
+
+            *self.last_seen.lock().unwrap() = Instant::now();
+
+            let reply = match serde_json::from_str(&line?) {
+                Ok(ClientMessage::Ping { number }) => ServerMessage::Pong { number },
+                Ok(msg) => return Ok(Some(msg)),
+                Err(e) => {
+                    warn!("Client sent a message that couldn't be parsed: {e}");
+                    ServerMessage::InvalidMessage { error: e.to_string() }
+                }
+            };
+
+            inner.send(&serde_json::to_string(&reply)?).await?;
         }
     }
 
@@ -55,7 +113,30 @@ impl ClientConnection {
     }
 }
 
-pub type ClientId = SocketAddr;
+/// An opaque ID the server gives each player when they join. Unlike their address, it stays the
+/// same if they rejoin a match on a new connection.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClientId(u64);
+
+impl ClientId {
+    /// Generates a new random ID.
+    pub fn new() -> Self {
+        Self(random_seed())
+    }
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+// Written in hex to match the Display impl, so IDs are easy to match up in the logs
+impl fmt::Debug for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ClientId({self})")
+    }
+}
 
 /// The global state for the server, to be shared among all client connections.
 ///
@@ -64,40 +145,130 @@ pub type ClientId = SocketAddr;
 /// order, there will be no deadlocks.
 #[derive(Debug, Default)]
 pub struct SharedState {
-    // TODO: replace this with some more sophisticated matchmaking
-    /// The current client thread that is waiting for matchmaking
+    /// The card list used to check players' decks.
+    pub cards: CardCatalog,
+    /// The stages that matches are played on.
+    pub stages: StageCatalog,
+    /// How long players have to make their moves.
+    pub timers: TimerConfig,
+    /// Where matches are recorded to, or None to not record them.
+    pub replays: Option<PathBuf>,
+    /// The player each open connection belongs to.
+    pub connections: Mutex<HashMap<SocketAddr, ClientId>>,
     pub players: Mutex<HashMap<ClientId, PublicPlayerInfo>>,
     pub channels: Mutex<HashMap<ClientId, UnboundedSender<GameEvent>>>,
-    pub hotseat: Mutex<Option<ClientId>>,
+    /// The players who are looking for a match.
+    pub matchmaker: Mutex<Matchmaker>,
+    /// Private rooms waiting for a second player, and the players who opened them.
+    pub rooms: Mutex<HashMap<RoomCode, ClientId>>,
+    /// The client each session token currently belongs to.
+    pub sessions: Mutex<HashMap<SessionToken, ClientId>>,
+    /// Matches in progress that can be rejoined, by the session tokens of their players.
+    pub resumable: Mutex<HashMap<SessionToken, UnboundedSender<Resume>>>,
+    /// Matches in progress that can be spectated.
+    pub matches: Mutex<HashMap<MatchId, LiveMatch>>,
 }
 
 impl SharedState {
-    fn new() -> Self {
-        Self::default()
+    fn new(timers: TimerConfig) -> Self {
+        Self {
+            cards: CardCatalog::standard(),
+            timers,
+            stages: StageCatalog::standard(),
+            replays: Some(PathBuf::from(REPLAY_DIR)),
+            matchmaker: Mutex::new(Matchmaker::new(MatchmakingConfig::default())),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the session token of the given client.
+    pub async fn token(&self, id: ClientId) -> Option<SessionToken> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .iter()
+            .find(|&(_, &i)| i == id)
+            .map(|(token, _)| token.clone())
+    }
+
+    /// Opens a new private room hosted by the given player, returning its code.
+    async fn create_room(&self, host: ClientId) -> RoomCode {
+        let mut rooms = self.rooms.lock().await;
+        let mut rng = Rng::new(random_seed());
+
+        loop {
+            let code = room_code(&mut rng);
+
+            if !rooms.contains_key(&code) {
+                rooms.insert(code.clone(), host);
+                return code;
+            }
+        }
+    }
+
+    /// Starts a match for each pair of players.
+    fn start_matches(self: &Arc<Self>, matches: Vec<[ClientId; 2]>) {
+        for players in matches {
+            info!("Matched {} with {}", players[0], players[1]);
+
+            // Create a new task to handle the new game. It'll inform both client tasks that the
+            // game has started, and go from there.
+            tokio::spawn(handle_game(Arc::clone(self), players));
+        }
+    }
+
+    /// Puts a player back in the matchmaking queue after the match they were found couldn't start.
+    pub async fn requeue(self: &Arc<Self>, id: ClientId) {
+        let Some(name) = self.players.lock().await.get(&id).map(|info| info.name.clone()) else {
+            return;
+        };
+
+        let mut matchmaker = self.matchmaker.lock().await;
+        let rating = matchmaker.rating(&name);
+        let found = matchmaker.join(id, rating, Instant::now());
+        drop(matchmaker);
+
+        self.start_matches(found.into_iter().collect());
     }
 
     // Handles a player disconnect by removing any of their data from the global state.
     #[instrument(skip(self))]
-    async fn remove_connection(&self, addr: ClientId) {
+    async fn remove_connection(&self, addr: SocketAddr) {
         info!("Removing disconnected client");
+        let mut connections = self.connections.lock().await;
         let mut players = self.players.lock().await;
         let mut channels = self.channels.lock().await;
-        let mut hotseat = self.hotseat.lock().await;
+        let mut matchmaker = self.matchmaker.lock().await;
+        let mut rooms = self.rooms.lock().await;
+        let mut sessions = self.sessions.lock().await;
 
-        players.remove(&addr);
-        channels.remove(&addr);
+        let Some(id) = connections.remove(&addr) else {
+            return;
+        };
 
-        if hotseat.is_some_and(|a| a == addr) {
-            hotseat.take();
-            info!("Client was on the hotseat, now it is free");
+        // If the player rejoined a match on a new connection, their data belongs to that one now
+        if connections.values().any(|&i| i == id) {
+            return;
         }
+
+        players.remove(&id);
+        channels.remove(&id);
+        sessions.retain(|_, i| *i != id);
+
+        if matchmaker.cancel(id) {
+            info!("Client was looking for a match, removed it from the queue");
+        }
+
+        matchmaker.forget(id);
+
+        rooms.retain(|_, host| *host != id);
     }
 }
 
 /// Continually accepts connections from clients, spawning a new task that handles the client in
 /// parallel.
-async fn mainloop(listener: TcpListener) -> color_eyre::Result<()> {
-    let shared = Arc::new(SharedState::new());
+async fn mainloop(listener: TcpListener, timers: TimerConfig) -> color_eyre::Result<()> {
+    let shared = Arc::new(SharedState::new(timers));
+    tokio::spawn(matchmaking_loop(Arc::clone(&shared)));
 
     loop {
         let (socket, addr) = listener.accept().await?;
@@ -119,8 +290,20 @@ async fn mainloop(listener: TcpListener) -> color_eyre::Result<()> {
     }
 }
 
-/// Runs the server on the given IP address.
-pub async fn run(address: &str) -> color_eyre::Result<()> {
+/// Periodically matches up players who have been waiting in the queue, since they may be able to
+/// play each other now when they couldn't before.
+async fn matchmaking_loop(shared_state: Arc<SharedState>) {
+    let mut interval = tokio::time::interval(MATCHMAKING_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        let matches = shared_state.matchmaker.lock().await.find_matches(Instant::now());
+        shared_state.start_matches(matches);
+    }
+}
+
+/// Runs the server on the given IP address, giving players the given amount of time to move.
+pub async fn run(address: &str, timers: TimerConfig) -> color_eyre::Result<()> {
     let listener = TcpListener::bind(address)
         .await
         .wrap_err(format!("Failed to listen on given address {address:?}"))?;
@@ -133,16 +316,27 @@ pub async fn run(address: &str) -> color_eyre::Result<()> {
             // Graceful shutdown goes here
             info!("Shutting down...");
         },
-        res = mainloop(listener) => return res,
+        res = mainloop(listener, timers) => return res,
     }
 
     Ok(())
 }
 
+/// Generates a random room code.
+fn room_code(rng: &mut Rng) -> RoomCode {
+    (0..ROOM_CODE_LEN)
+        .map(|_| ROOM_CODE_CHARS[rng.below(ROOM_CODE_CHARS.len())] as char)
+        .collect()
+}
+
 #[derive(Clone, Debug)]
 enum ClientState {
     InLobby,
     Matchmaking,
+    /// Waiting in a private room for someone to join.
+    InRoom(RoomCode),
+    /// Watching someone else's match.
+    Spectating,
 }
 
 /// Async task which handles a client connection.
@@ -154,20 +348,67 @@ async fn handle_client(
 ) -> color_eyre::Result<()> {
     let mut state = ClientState::InLobby;
     let connection = Arc::new(connection);
-
-    // Get the player info
-    let Some(ClientMessage::HelloServer { info }) = connection.next().await? else {
-        warn!("Client did not say hello. Rude! Disconnecting (Client is not following protocol)");
-        return Ok(());
+    // The messages from the match being spectated, if there is one
+    let mut spectating: Option<UnboundedReceiver<ServerMessage>> = None;
+
+    // Get the player info, or the match they are rejoining
+    let (id, info, token, resume) = match connection.next().await? {
+        Some(ClientMessage::HelloServer { info }) => {
+            let id = ClientId::new();
+            let token = format!("{:016x}{:016x}", random_seed(), random_seed());
+            connection.send(&ServerMessage::HelloClient { token: token.clone() }).await?;
+            info!("Player {:?} has joined the lobby with ID {id}", info.name);
+
+            (id, info, token, None)
+        }
+        Some(ClientMessage::Resume { token }) => {
+            let Some((id, info, resume)) = find_match(&shared_state, &token).await else {
+                warn!("Client tried to rejoin a match that doesn't exist");
+                connection.send(&ServerMessage::ResumeFailed).await?;
+                return Ok(());
+            };
+
+            info!("Player {:?} ({id}) is rejoining their match", info.name);
+            (id, info, token, Some(resume))
+        }
+        _ => {
+            warn!("Client did not say hello. Rude! Disconnecting (Client is not following protocol)");
+            return Ok(());
+        }
     };
 
-    connection.send(&ServerMessage::HelloClient).await?;
-    info!("Player {:?} has joined the lobby", info.name);
-
     let (tx, mut rx) = unbounded_channel();
 
-    shared_state.players.lock().await.insert(addr, info.clone());
-    shared_state.channels.lock().await.insert(addr, tx);
+    shared_state.connections.lock().await.insert(addr, id);
+    shared_state.players.lock().await.insert(id, info.clone());
+    let old_channel = shared_state.channels.lock().await.insert(id, tx.clone());
+    shared_state.sessions.lock().await.insert(token.clone(), id);
+
+    if let Some(game) = resume {
+        let resume = Resume {
+            token,
+            connection: Arc::clone(&connection),
+            events: tx,
+        };
+
+        if game.send(resume).is_err() {
+            warn!("Match ended before the client could rejoin it");
+            connection.send(&ServerMessage::ResumeFailed).await?;
+            return Ok(());
+        } else if wait_for_game_end(&mut rx).await? {
+            info!("Game has ended, returning to lobby.");
+        } else {
+            warn!("Match turned the client away since the player is still connected");
+
+            // The player's events still go to their live connection
+            if let Some(old_channel) = old_channel {
+                shared_state.channels.lock().await.insert(id, old_channel);
+            }
+
+            connection.send(&ServerMessage::ResumeFailed).await?;
+            return Ok(());
+        }
+    }
 
     // Continually poll for either messages from the client or events from other parts of the
     // server.
@@ -185,25 +426,97 @@ async fn handle_client(
                 match (&msg, &state) {
                     (ClientMessage::FindGame, ClientState::InLobby) => {
                         state = ClientState::Matchmaking;
-                        let mut hotseat = shared_state.hotseat.lock().await;
+                        let mut matchmaker = shared_state.matchmaker.lock().await;
+                        let rating = matchmaker.rating(&info.name);
+                        let found = matchmaker.join(id, rating, Instant::now());
+                        drop(matchmaker);
 
-                        match *hotseat {
+                        match found {
                             None => {
-                                info!("Nobody is on the hotseat, so I'm siting down");
-                                *hotseat = Some(addr);
+                                info!("Nobody to play against yet, joining the queue");
                                 connection.send(&ServerMessage::WaitForOpponent).await?;
                             }
-                            Some(opp) => {
-                                *hotseat = None;
-                                drop(hotseat);
+                            Some(players) => shared_state.start_matches(vec![players]),
+                        }
+                    }
 
-                                // Create a new task to handle the new game. It'll inform both
-                                // client tasks that the game has started, and go from there.
-                                tokio::spawn(handle_game(Arc::clone(&shared_state), [addr, opp]));
+                    (ClientMessage::CancelFindGame, ClientState::Matchmaking) => {
+                        // If we're no longer in the queue then a match has already been found,
+                        // and the game handler will be in touch shortly
+                        if shared_state.matchmaker.lock().await.cancel(id) {
+                            info!("Left the matchmaking queue");
+                            state = ClientState::InLobby;
+                            connection.send(&ServerMessage::FindGameCancelled).await?;
+                        }
+                    }
+
+                    (ClientMessage::CreateRoom, ClientState::InLobby) => {
+                        let code = shared_state.create_room(id).await;
+                        info!("Opened room {code}");
+                        connection.send(&ServerMessage::RoomCreated { code: code.clone() }).await?;
+                        state = ClientState::InRoom(code);
+                    }
+
+                    (ClientMessage::JoinRoom { code }, ClientState::InLobby) => {
+                        let code = code.trim().to_uppercase();
+                        let mut rooms = shared_state.rooms.lock().await;
+
+                        match rooms.get(&code) {
+                            Some(&host) if host != id => {
+                                rooms.remove(&code);
+                                drop(rooms);
+
+                                info!("Joined room {code}");
+                                state = ClientState::Matchmaking;
+                                shared_state.start_matches(vec![[host, id]]);
+                            }
+                            _ => {
+                                drop(rooms);
+                                connection.send(&ServerMessage::RoomNotFound).await?;
                             }
                         }
                     }
 
+                    (ClientMessage::LeaveRoom, ClientState::InRoom(code)) => {
+                        // If the room is gone then someone has already joined, and the game
+                        // handler will be in touch shortly
+                        if shared_state.rooms.lock().await.remove(code).is_some() {
+                            info!("Closed room {code}");
+                            state = ClientState::InLobby;
+                            connection.send(&ServerMessage::RoomClosed).await?;
+                        }
+                    }
+
+                    (ClientMessage::ListMatches, ClientState::InLobby) => {
+                        let matches = shared_state.matches.lock().await.iter()
+                            .map(|(&id, m)| MatchSummary { id, players: m.players.clone() })
+                            .collect();
+
+                        connection.send(&ServerMessage::MatchList { matches }).await?;
+                    }
+
+                    (ClientMessage::Spectate { id: match_id }, ClientState::InLobby) => {
+                        let (tx, rx) = unbounded_channel();
+                        let sent = shared_state.matches.lock().await
+                            .get(match_id)
+                            .is_some_and(|m| m.spectate.send(tx).is_ok());
+
+                        if sent {
+                            // The game handler will send a snapshot of the match to start with
+                            info!("Spectating match {match_id}");
+                            spectating = Some(rx);
+                            state = ClientState::Spectating;
+                        } else {
+                            connection.send(&ServerMessage::MatchNotFound).await?;
+                        }
+                    }
+
+                    (ClientMessage::StopSpectating, ClientState::Spectating) => {
+                        spectating = None;
+                        state = ClientState::InLobby;
+                        connection.send(&ServerMessage::SpectatingEnded).await?;
+                    }
+
                     _ => {
                         // When the client breaks protocol, we will ignore it to allow things like sending
                         // the same message twice. But log it just to be sure.
@@ -212,9 +525,21 @@ async fn handle_client(
                 }
             },
 
+            msg = recv_spectated(&mut spectating), if spectating.is_some() => {
+                match msg {
+                    Some(msg) => connection.send(&msg).await?,
+                    None => {
+                        info!("Spectated match has ended, returning to lobby.");
+                        spectating = None;
+                        state = ClientState::InLobby;
+                        connection.send(&ServerMessage::SpectatingEnded).await?;
+                    }
+                }
+            },
+
             Some(ev) = rx.recv() => {
                 match ev {
-                    GameEvent::MatchFound(tx) if matches!(state, ClientState::Matchmaking) => {
+                    GameEvent::MatchFound(tx) if matches!(state, ClientState::Matchmaking | ClientState::InRoom(_)) => {
                         if tx.send(Arc::clone(&connection)).is_err() {
                             error!("Couldn't send connection over to the game handler!");
                             state = ClientState::InLobby;
@@ -224,16 +549,7 @@ async fn handle_client(
                             info!("Game has started, relinquishing connection to handler thread.");
                             info!("Now waiting for a game over event...");
 
-                            match rx.recv().await {
-                                Some(GameEvent::GameEnded) => {},
-                                Some(e) => {
-                                    // A hard error since this would be a wildly invalid state
-                                    return Err(eyre!("Expected a GameEnded event, got something different ({e:?})"));
-                                }
-                                None => {
-                                    error!("Game channel closed unexpectedly");
-                                }
-                            }
+                            wait_for_game_end(&mut rx).await?;
 
                             info!("Game has ended, returning to lobby.");
                             state = ClientState::InLobby;
@@ -251,3 +567,205 @@ async fn handle_client(
 
     Ok(())
 }
+
+/// Receives the next message from the match being spectated, or `None` when the match is over.
+async fn recv_spectated(spectating: &mut Option<UnboundedReceiver<ServerMessage>>) -> Option<ServerMessage> {
+    match spectating {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits for the game handler to say that the client's match is over. Returns false if the match
+/// turned away the client's attempt to rejoin it instead.
+async fn wait_for_game_end(rx: &mut UnboundedReceiver<GameEvent>) -> color_eyre::Result<bool> {
+    match rx.recv().await {
+        Some(GameEvent::GameEnded) => {}
+        Some(GameEvent::ResumeRejected) => return Ok(false),
+        Some(e) => {
+            // A hard error since this would be a wildly invalid state
+            return Err(eyre!(
+                "Expected a GameEnded event, got something different ({e:?})"
+            ));
+        }
+        None => {
+            error!("Game channel closed unexpectedly");
+        }
+    }
+
+    Ok(true)
+}
+
+/// Finds the match that the session token belongs to, if it can still be rejoined. Returns the
+/// player's ID and info along with where to send their new connection.
+async fn find_match(
+    shared_state: &SharedState,
+    token: &SessionToken,
+) -> Option<(ClientId, PublicPlayerInfo, UnboundedSender<Resume>)> {
+    let players = shared_state.players.lock().await;
+    let sessions = shared_state.sessions.lock().await;
+    let resumable = shared_state.resumable.lock().await;
+
+    let game = resumable.get(token).filter(|tx| !tx.is_closed())?;
+    let id = *sessions.get(token)?;
+    let info = players.get(&id)?;
+
+    Some((id, info.clone(), game.clone()))
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+
+    /// Returns a connection as seen by the server, and the client's end of the socket.
+    async fn connect(idle_timeout: Duration) -> (ClientConnection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+
+        (
+            ClientConnection::with_idle_timeout(socket, idle_timeout),
+            client,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_answered() {
+        let (connection, client) = connect(IDLE_TIMEOUT).await;
+        let (read, mut write) = client.into_split();
+
+        write
+            .write_all(b"{\"type\":\"ping\",\"number\":42}\n{\"type\":\"find_game\"}\n")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            connection.next().await.unwrap(),
+            Some(ClientMessage::FindGame)
+        );
+
+        let mut line = String::new();
+        BufReader::new(read).read_line(&mut line).await.unwrap();
+        assert_eq!(line, "{\"type\":\"pong\",\"number\":42}\n");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_message_is_skipped() {
+        let (connection, client) = connect(IDLE_TIMEOUT).await;
+        let (read, mut write) = client.into_split();
+
+        write
+            .write_all(b"{\"type\":\"fire_ze_missiles\"}\nnot json\n{\"type\":\"find_game\"}\n")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            connection.next().await.unwrap(),
+            Some(ClientMessage::FindGame)
+        );
+
+        let mut lines = BufReader::new(read).lines();
+        for _ in 0..2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let reply: ServerMessage = serde_json::from_str(&line).unwrap();
+            assert!(matches!(reply, ServerMessage::InvalidMessage { .. }), "{reply:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_silent_client_times_out() {
+        let (connection, mut client) = connect(Duration::from_millis(100)).await;
+
+        client
+            .write_all(b"{\"type\":\"ping\",\"number\":1}\n")
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        assert_eq!(connection.next().await.unwrap(), None);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_remove_connection_keeps_rejoined_player() {
+        let shared_state = SharedState::new(TimerConfig::default());
+        let id = ClientId::new();
+        let old: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let new: SocketAddr = "127.0.0.1:2000".parse().unwrap();
+
+        shared_state.connections.lock().await.insert(old, id);
+        shared_state.connections.lock().await.insert(new, id);
+        shared_state
+            .players
+            .lock()
+            .await
+            .insert(id, PublicPlayerInfo::default());
+        shared_state
+            .sessions
+            .lock()
+            .await
+            .insert("token".to_string(), id);
+        shared_state
+            .matchmaker
+            .lock()
+            .await
+            .join(id, None, Instant::now());
+
+        shared_state.remove_connection(old).await;
+        assert!(shared_state.players.lock().await.contains_key(&id));
+        assert_eq!(shared_state.token(id).await, Some("token".to_string()));
+
+        shared_state.remove_connection(new).await;
+        assert!(shared_state.players.lock().await.is_empty());
+        assert!(shared_state.sessions.lock().await.is_empty());
+        assert!(!shared_state.matchmaker.lock().await.contains(id));
+    }
+
+    #[tokio::test]
+    async fn test_match_with_departed_player_requeues_the_other() {
+        let shared_state = Arc::new(SharedState::new(TimerConfig::default()));
+        let [stayed, left] = [ClientId::new(), ClientId::new()];
+        let (tx, mut rx) = unbounded_channel();
+
+        shared_state
+            .players
+            .lock()
+            .await
+            .insert(stayed, PublicPlayerInfo::default());
+        shared_state.channels.lock().await.insert(stayed, tx);
+
+        handle_game(Arc::clone(&shared_state), [stayed, left]).await;
+
+        assert!(shared_state.matchmaker.lock().await.contains(stayed));
+        assert!(!shared_state.matchmaker.lock().await.contains(left));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_room_code() {
+        let mut rng = Rng::new(0);
+        let code = room_code(&mut rng);
+
+        assert_eq!(code.len(), ROOM_CODE_LEN);
+        assert!(code.bytes().all(|c| ROOM_CODE_CHARS.contains(&c)));
+        assert_ne!(code, room_code(&mut rng));
+    }
+
+    #[tokio::test]
+    async fn test_room_closes_when_host_leaves() {
+        let shared_state = SharedState::new(TimerConfig::default());
+        let id = ClientId::new();
+        let addr: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+
+        shared_state.connections.lock().await.insert(addr, id);
+        let code = shared_state.create_room(id).await;
+        assert_eq!(shared_state.rooms.lock().await.get(&code), Some(&id));
+
+        shared_state.remove_connection(addr).await;
+        assert!(shared_state.rooms.lock().await.is_empty());
+    }
+}
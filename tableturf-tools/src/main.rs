@@ -0,0 +1,265 @@
+//! Command line tools for looking at boards, cards and replays without the graphical client.
+
+mod render;
+
+use std::io::{self, IsTerminal};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use tableturf::{
+    board::Rotation,
+    cards::CardCatalog,
+    game::{Action, Event, GameState, PlayerMove},
+    map,
+    protocol::PlayerId,
+    replay::Replay,
+    stages::StageCatalog,
+};
+
+const USAGE: &str = "\
+Usage: tableturf-tools [--no-colour] <command>
+
+Commands:
+    board <map file>        Draws the board in a map file
+    stages                  Lists the standard stages
+    stage <id or name>      Draws one of the standard stages
+    card <id>               Draws a card in every rotation
+    replay <file>           Steps through a replay, showing both moves and the board after each
+                            turn
+        --turn <n>          Only show turn n (0 is the start of the match)
+        --step              Wait for enter after each turn
+
+Colour is turned off when the output isn't a terminal, or if NO_COLOR is set.";
+
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let colour = !take_flag(&mut args, "--no-colour")
+        && std::env::var_os("NO_COLOR").is_none()
+        && io::stdout().is_terminal();
+
+    if args.is_empty() {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let command = args.remove(0);
+    let args = &mut args;
+
+    match command.as_str() {
+        "board" => {
+            let path = arg(args, "map file")?;
+            let input =
+                std::fs::read_to_string(&path).wrap_err(format!("Couldn't read {path:?}"))?;
+            let board = map::parse(&input).map_err(|e| eyre!("{path}:{e}"))?;
+
+            println!("{}", board.name());
+            print!("{}", render::board(&board, colour));
+        }
+        "stages" => {
+            for stage in StageCatalog::standard().stages() {
+                println!("{:>2}  {}", stage.id(), stage.name());
+            }
+        }
+        "stage" => {
+            let stages = StageCatalog::standard();
+            let query = arg(args, "stage")?;
+            let stage = match query.parse() {
+                Ok(id) => stages.get(id),
+                Err(_) => stages.by_name(&query),
+            }
+            .ok_or_else(|| eyre!("There is no stage {query:?}"))?;
+
+            println!("{}  {}", stage.id(), stage.name());
+            print!("{}", render::board(stage.board(), colour));
+        }
+        "card" => {
+            let cards = CardCatalog::standard();
+            let id = arg(args, "card id")?
+                .parse()
+                .wrap_err("Card IDs are numbers")?;
+            let card = cards
+                .get(id)
+                .ok_or_else(|| eyre!("There is no card {id}"))?;
+
+            println!(
+                "{}  {} (cost {}, {} squares)",
+                card.id(),
+                card.name(),
+                card.cost(),
+                card.square_count()
+            );
+
+            for rotation in Rotation::ALL {
+                println!("\n{rotation:?}");
+                print!("{}", render::footprint(card.footprint(rotation), colour));
+            }
+        }
+        "replay" => {
+            let turn = match take_option(args, "--turn")? {
+                Some(n) => Some(n.parse().wrap_err("--turn takes a number")?),
+                None => None,
+            };
+            let step = take_flag(args, "--step");
+            let path = arg(args, "replay file")?;
+
+            replay(&path, turn, step, colour)?;
+        }
+        _ => {
+            println!("{USAGE}");
+            bail!("Unknown command {command:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a flag from the arguments, returning whether it was there.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|a| a != flag);
+    args.len() != len
+}
+
+/// Removes an option and its value from the arguments, returning the value if it was there.
+fn take_option(args: &mut Vec<String>, option: &str) -> color_eyre::Result<Option<String>> {
+    let Some(i) = args.iter().position(|a| a == option) else {
+        return Ok(None);
+    };
+
+    if i + 1 >= args.len() {
+        bail!("{option} needs a value");
+    }
+
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+/// Returns the next positional argument.
+fn arg(args: &mut Vec<String>, name: &str) -> color_eyre::Result<String> {
+    if args.is_empty() {
+        bail!("Missing argument: {name}\n\n{USAGE}");
+    }
+
+    Ok(args.remove(0))
+}
+
+/// Prints a replay turn by turn.
+fn replay(path: &str, only_turn: Option<u32>, step: bool, colour: bool) -> color_eyre::Result<()> {
+    let input = std::fs::read_to_string(path).wrap_err(format!("Couldn't read {path:?}"))?;
+    let replay = Replay::parse(&input).map_err(|e| eyre!("{path}: {e}"))?;
+    let cards = CardCatalog::standard();
+    let mut player = replay.start(&cards).map_err(|e| eyre!("{path}: {e}"))?;
+
+    let header = &replay.header;
+    let names = [
+        render::player(PlayerId::P1, &header.players[0], colour),
+        render::player(PlayerId::P2, &header.players[1], colour),
+    ];
+
+    println!("{} vs {}", names[0], names[1]);
+    println!("Seed {}, {} actions", header.seed, replay.actions.len());
+
+    let show = |turn: u32| only_turn.is_none_or(|t| t == turn);
+
+    if show(0) {
+        println!("\nStart of the match");
+        print!("{}", render::board(player.game().board(), colour));
+        wait(step)?;
+    }
+
+    while let Some(result) = player.step() {
+        let (action, events) = result.map_err(|e| eyre!("{path}: {e}"))?;
+
+        if let Action::Redraw { player, redraw } = action {
+            if only_turn.is_none() {
+                let choice = if redraw { "redrew" } else { "kept" };
+                println!("{} {choice} their opening hand", names[player as usize]);
+            }
+        }
+
+        for event in events {
+            match event {
+                Event::TurnResolved { turn, moves, .. } if show(turn) => {
+                    println!("\nTurn {turn}");
+
+                    for p in [PlayerId::P1, PlayerId::P2] {
+                        println!(
+                            "  {}: {}",
+                            names[p as usize],
+                            describe_move(&cards, &moves[p as usize])
+                        );
+                    }
+
+                    print!("{}", render::board(player.game().board(), colour));
+                    print_status(player.game(), &names);
+                    wait(step)?;
+                }
+                Event::Forfeited { player } => println!("\n{} forfeited", names[player as usize]),
+                Event::GameOver { scores, winner } => {
+                    let result = match winner {
+                        Some(p) => format!("{} wins", names[p as usize]),
+                        None => "Draw".to_string(),
+                    };
+                    println!("\nGame over: {result} ({} - {})", scores[0], scores[1]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if !player.game().is_finished() {
+        println!("\nThe replay ends before the match does");
+    }
+
+    Ok(())
+}
+
+fn describe_move(cards: &CardCatalog, mv: &PlayerMove) -> String {
+    let name = |id| {
+        cards
+            .get(id)
+            .map_or_else(|| format!("card {id}"), |c| c.name().to_string())
+    };
+
+    match *mv {
+        PlayerMove::Pass { card } => format!("passed with {}", name(card)),
+        PlayerMove::Place {
+            card,
+            rotation,
+            anchor,
+            special,
+        } => {
+            let special = if special { " as a special attack" } else { "" };
+            format!(
+                "placed {} at ({}, {}) facing {rotation:?}{special}",
+                name(card),
+                anchor.x,
+                anchor.y
+            )
+        }
+    }
+}
+
+fn print_status(game: &GameState, names: &[String; 2]) {
+    let scores = game.scores();
+
+    for p in [PlayerId::P1, PlayerId::P2] {
+        println!(
+            "  {}: {} squares, {} special points",
+            names[p as usize],
+            scores[p as usize],
+            game.special_points(p)
+        );
+    }
+}
+
+/// Waits for the user to press enter, if stepping through a replay.
+fn wait(step: bool) -> color_eyre::Result<()> {
+    if step {
+        io::stdin().read_line(&mut String::new())?;
+    }
+
+    Ok(())
+}
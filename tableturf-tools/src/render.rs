@@ -0,0 +1,150 @@
+//! Drawing boards and cards as text for a terminal.
+//!
+//! With colour turned on, every tile is drawn as two coloured spaces so that the board comes out
+//! roughly square. Without colour, boards are drawn with the characters of the map format (see
+//! [`tableturf::map`]) and cards with the characters of the card pattern format, so the output
+//! can be pasted straight into a map or card file.
+
+use std::fmt::Write;
+
+use tableturf::{
+    board::{Board, Coord, Tile},
+    cards::{Footprint, Square},
+    map::tile_char,
+    protocol::PlayerId,
+};
+
+const RESET: &str = "\x1b[0m";
+
+/// The background colour (from the 256 colour palette) and text of a coloured tile.
+fn tile_style(tile: Option<Tile>) -> (Option<u8>, &'static str) {
+    match tile {
+        None => (None, "  "),
+        Some(Tile::Empty) => (Some(236), "  "),
+        Some(Tile::Wall) => (Some(250), "  "),
+        Some(Tile::Conflict) => (Some(244), "><"),
+        Some(Tile::Ink { owner, special }) => match (owner, special) {
+            (PlayerId::P1, false) => (Some(220), "  "),
+            (PlayerId::P1, true) => (Some(208), "()"),
+            (PlayerId::P2, false) => (Some(33), "  "),
+            (PlayerId::P2, true) => (Some(51), "()"),
+        },
+    }
+}
+
+fn write_cell(out: &mut String, (background, text): (Option<u8>, &str)) {
+    match background {
+        Some(n) => write!(out, "\x1b[48;5;{n}m\x1b[30m{text}{RESET}").unwrap(),
+        None => out.push_str(text),
+    }
+}
+
+/// Draws the board, one line per row.
+pub fn board(board: &Board, colour: bool) -> String {
+    let mut out = String::new();
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let tile = board.get(Coord::new(x, y));
+
+            if colour {
+                write_cell(&mut out, tile_style(tile));
+            } else {
+                out.push(tile_char(tile));
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Draws a card's squares in one rotation, in P1's colours.
+pub fn footprint(footprint: &Footprint, colour: bool) -> String {
+    let mut out = String::new();
+
+    for y in 0..footprint.height {
+        for x in 0..footprint.width {
+            let square = footprint.get(Coord::new(x, y));
+
+            if colour {
+                let tile = match square {
+                    Square::Empty => Some(Tile::Empty),
+                    Square::Ink | Square::Special => Some(Tile::Ink {
+                        owner: PlayerId::P1,
+                        special: square == Square::Special,
+                    }),
+                };
+                write_cell(&mut out, tile_style(tile));
+            } else {
+                out.push(match square {
+                    Square::Empty => '.',
+                    Square::Ink => '#',
+                    Square::Special => '*',
+                });
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Names a player in their colour.
+pub fn player(player: PlayerId, name: &str, colour: bool) -> String {
+    let n = match player {
+        PlayerId::P1 => 220,
+        PlayerId::P2 => 33,
+    };
+
+    if colour {
+        format!("\x1b[38;5;{n}m{name}{RESET}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tableturf::{board::Rotation, cards::CardCatalog, map};
+
+    use super::*;
+
+    const TINY: &str = "name: Tiny Stage\n-...b-\n..#B..\n..A#..\n-a..x-\n";
+
+    #[test]
+    fn test_plain_board_is_map_format() {
+        let board = map::parse(TINY).unwrap();
+        let drawn = super::board(&board, false);
+
+        assert_eq!(format!("name: Tiny Stage\n{drawn}"), TINY);
+    }
+
+    #[test]
+    fn test_coloured_board() {
+        let board = map::parse(TINY).unwrap();
+        let drawn = super::board(&board, true);
+        let first = drawn.lines().next().unwrap();
+
+        // Tiles outside the stage are left blank, and every coloured tile is reset afterwards
+        assert!(first.starts_with("  \x1b[48;5;236m"));
+        assert_eq!(first.matches("\x1b[48;5;").count(), 4);
+        assert_eq!(first.matches(RESET).count(), 4);
+        assert_eq!(drawn.lines().count(), 4);
+    }
+
+    #[test]
+    fn test_footprint() {
+        let cards = CardCatalog::standard();
+        let card = cards.cards().next().unwrap();
+        let footprint = card.footprint(Rotation::Up);
+        let drawn = super::footprint(footprint, false);
+
+        assert_eq!(drawn.lines().count(), footprint.height as usize);
+        assert_eq!(
+            drawn.chars().filter(|&c| c == '#' || c == '*').count(),
+            card.square_count()
+        );
+        assert_eq!(drawn.matches('*').count(), footprint.specials().count());
+    }
+}
@@ -0,0 +1,111 @@
+//! The hash map board compared with the bitboard, on the largest standard stages part way
+//! through a match.
+
+mod common;
+
+use common::{largest_stages, midgame};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tableturf::{
+    ai::{Bot, GreedyBot, PlayerView},
+    bitboard::{self, BitBoard},
+    board::{Board, Coord, PlacementError, Rotation},
+    cards::Card,
+    game::{Action, GameState, PlayerMove},
+    protocol::PlayerId,
+    turn::{self, Move, Placement},
+};
+
+/// The move the greedy bot would make.
+fn greedy_move(game: &GameState, player: PlayerId) -> Move<'_> {
+    let Action::Play {
+        mv:
+            PlayerMove::Place {
+                card,
+                rotation,
+                anchor,
+                special,
+            },
+        ..
+    } = GreedyBot.choose_move(&PlayerView::new(game, player))
+    else {
+        return Move::Pass;
+    };
+
+    Move::Place(Placement {
+        card: game.hand(player).iter().find(|c| c.id() == card).unwrap(),
+        rotation,
+        anchor,
+        special,
+    })
+}
+
+/// Checks every card in the hand in every rotation at every position, returning how many fit.
+fn check_every_placement(
+    game: &GameState,
+    check: impl Fn(&Card, Rotation, Coord) -> Result<(), PlacementError>,
+) -> usize {
+    let board = game.board();
+    let mut count = 0;
+
+    for card in game.hand(PlayerId::P1) {
+        for rotation in Rotation::ALL {
+            for y in 0..board.height() {
+                for x in 0..board.width() {
+                    count += check(card, rotation, Coord::new(x, y)).is_ok() as usize;
+                }
+            }
+        }
+    }
+
+    count
+}
+
+fn bench_bitboard(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bitboard");
+
+    for stage in largest_stages(2) {
+        let game = midgame(&stage);
+        let board = game.board();
+        let bitboard = BitBoard::try_from(board).unwrap();
+        let moves = [
+            greedy_move(&game, PlayerId::P1),
+            greedy_move(&game, PlayerId::P2),
+        ];
+        let name = stage.name();
+
+        group.bench_with_input(BenchmarkId::new("convert", name), &(), |b, _| {
+            b.iter(|| Board::from(&BitBoard::try_from(board).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("clone/hashmap", name), &(), |b, _| {
+            b.iter(|| board.clone())
+        });
+        group.bench_with_input(BenchmarkId::new("clone/bitboard", name), &(), |b, _| {
+            b.iter(|| bitboard.clone())
+        });
+        group.bench_with_input(BenchmarkId::new("check/hashmap", name), &(), |b, _| {
+            b.iter(|| {
+                check_every_placement(&game, |c, r, a| {
+                    board.check_placement(c, r, a, PlayerId::P1)
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("check/bitboard", name), &(), |b, _| {
+            b.iter(|| {
+                check_every_placement(&game, |c, r, a| {
+                    bitboard.check_placement(c, r, a, PlayerId::P1)
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("resolve/hashmap", name), &(), |b, _| {
+            b.iter(|| turn::resolve_turn(board, &moves[0], &moves[1]).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("resolve/bitboard", name), &(), |b, _| {
+            b.iter(|| bitboard::resolve_turn(&bitboard, &moves[0], &moves[1]).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_bitboard);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+//! Match states shared by the benchmarks.
+
+use tableturf::{
+    ai::{Bot, GreedyBot, PlayerView},
+    board::Coord,
+    cards::CardCatalog,
+    game::GameState,
+    protocol::PlayerId,
+    stages::{Stage, StageCatalog},
+};
+
+/// The number of turns played before measuring, so that there is ink to place next to.
+const TURNS: u32 = 6;
+
+/// The standard stages with the most tiles.
+pub fn largest_stages(count: usize) -> Vec<Stage> {
+    let size = |stage: &Stage| {
+        let board = stage.board();
+        (0..board.height())
+            .flat_map(|y| (0..board.width()).map(move |x| Coord::new(x, y)))
+            .filter(|&c| board.get(c).is_some())
+            .count()
+    };
+
+    let mut stages: Vec<_> = StageCatalog::standard().stages().cloned().collect();
+    stages.sort_by_key(|stage| std::cmp::Reverse(size(stage)));
+    stages.truncate(count);
+    stages
+}
+
+/// A match on the stage a few turns in, played by two greedy bots.
+pub fn midgame(stage: &Stage) -> GameState {
+    let cards = CardCatalog::standard();
+    let deck = |ids: std::ops::Range<usize>| ids.map(|id| cards.get(id).unwrap().clone()).collect();
+    let mut game = GameState::new(stage.board().clone(), [deck(1..16), deck(10..25)], 1);
+
+    while game.turn() <= TURNS {
+        for player in [PlayerId::P1, PlayerId::P2] {
+            if game.is_waiting_for(player) {
+                let action = GreedyBot.choose_move(&PlayerView::new(&game, player));
+                game.apply(action).unwrap();
+            }
+        }
+    }
+
+    game
+}
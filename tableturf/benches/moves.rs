@@ -0,0 +1,89 @@
+//! Finding every legal move for a mid-match hand on the largest standard stages, compared with
+//! checking the same cards, rotations and positions one at a time.
+
+mod common;
+
+use common::{largest_stages, midgame};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tableturf::{
+    board::{Board, Coord},
+    cards::Card,
+    moves::{distinct_rotations, legal_moves, MoveFinder},
+    protocol::PlayerId,
+};
+
+/// Counts the same moves as [`legal_moves`] by checking every card in every distinct rotation at
+/// every position where it fits on the board with [`Board::check_placement`] (and
+/// [`Board::check_special_placement`] if the player has the points for it).
+fn check_every_placement(
+    board: &Board,
+    hand: &[Card],
+    player: PlayerId,
+    special_points: u32,
+) -> usize {
+    let mut count = 0;
+
+    for card in hand {
+        // Passing with the card
+        count += 1;
+
+        for (rotation, footprint) in distinct_rotations(card) {
+            for y in 0..=board.height() - footprint.height {
+                for x in 0..=board.width() - footprint.width {
+                    let anchor = Coord::new(x, y) + footprint.pivot;
+
+                    count += board
+                        .check_placement(card, rotation, anchor, player)
+                        .is_ok() as usize;
+
+                    if card.cost() <= special_points {
+                        count += board
+                            .check_special_placement(card, rotation, anchor, player)
+                            .is_ok() as usize;
+                    }
+                }
+            }
+        }
+    }
+
+    count
+}
+
+fn bench_moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("moves");
+
+    for stage in largest_stages(2) {
+        let game = midgame(&stage);
+        let board = game.board();
+        let hand = game.hand(PlayerId::P1);
+        let points = game.special_points(PlayerId::P1);
+
+        // Both ways of finding moves have to be doing the same work for the comparison to mean
+        // anything
+        assert_eq!(
+            legal_moves(board, hand, PlayerId::P1, points).len(),
+            check_every_placement(board, hand, PlayerId::P1, points)
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("legal_moves", stage.name()),
+            &(),
+            |b, _| b.iter(|| legal_moves(board, hand, PlayerId::P1, points)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("move_finder", stage.name()),
+            &(),
+            |b, _| b.iter(|| MoveFinder::new(board, PlayerId::P1)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("check_every_placement", stage.name()),
+            &(),
+            |b, _| b.iter(|| check_every_placement(board, hand, PlayerId::P1, points)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_moves);
+criterion_main!(benches);
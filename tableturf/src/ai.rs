@@ -0,0 +1,219 @@
+//! Computer opponents.
+//!
+//! Bots are given a [`PlayerView`] rather than the [`GameState`] itself, so that they can only
+//! act on what a real player could see from their seat: the board, their own hand, both decks and
+//! the cards that have been played. Decks are public, and networked players are sent both of them
+//! in [`ServerMessage::MatchStarted`]. The opponent's hand and the order of either draw pile are
+//! never visible.
+//!
+//! [`ServerMessage::MatchStarted`]: crate::protocol::ServerMessage::MatchStarted
+
+mod greedy;
+mod ismcts;
+
+use std::collections::BTreeSet;
+
+pub use greedy::GreedyBot;
+pub use ismcts::{Budget, IsmctsBot, IsmctsConfig};
+
+use crate::{
+    board::{Board, Coord},
+    cards::{Card, CardID},
+    game::{Action, GameState, PlayerMove, TURN_COUNT},
+    moves,
+    protocol::PlayerId,
+};
+
+/// Something that can play a match in place of a person.
+pub trait Bot {
+    /// Chooses the bot's action for the current point of the match: whether to redraw if the
+    /// first turn hasn't started yet, otherwise the move for this turn.
+    fn choose_move(&mut self, view: &PlayerView) -> Action;
+}
+
+/// Everything one player can see about a match.
+#[derive(Debug, Clone)]
+pub struct PlayerView {
+    pub player: PlayerId,
+    pub board: Board,
+    /// The current turn, starting at 1.
+    pub turn: u32,
+    /// Whether both players have decided whether to redraw, so turns are being played.
+    pub started: bool,
+    pub hand: Vec<Card>,
+    /// The number of cards the player has left to draw.
+    pub draw_pile_len: usize,
+    pub special_points: [u32; 2],
+    /// Special tiles that have already been activated, and so won't grant another point.
+    pub activated: BTreeSet<Coord>,
+    /// Both players' decks, in the order they were chosen. Decks are public, see
+    /// [`ServerMessage::MatchStarted`](crate::protocol::ServerMessage::MatchStarted).
+    pub decks: [Vec<Card>; 2],
+    /// The cards each player has used so far, in order.
+    pub played: [Vec<CardID>; 2],
+}
+
+impl PlayerView {
+    /// Returns what the player can see of the match.
+    pub fn new(game: &GameState, player: PlayerId) -> Self {
+        let board = game.board().clone();
+        let activated = board
+            .special_tiles()
+            .map(|(coord, _)| coord)
+            .filter(|&coord| game.is_activated(coord))
+            .collect();
+
+        Self {
+            player,
+            board,
+            turn: game.turn(),
+            started: game.has_started(),
+            hand: game.hand(player).to_vec(),
+            draw_pile_len: game.draw_pile_len(player),
+            special_points: [
+                game.special_points(PlayerId::P1),
+                game.special_points(PlayerId::P2),
+            ],
+            activated,
+            decks: [
+                game.deck(PlayerId::P1).to_vec(),
+                game.deck(PlayerId::P2).to_vec(),
+            ],
+            played: [
+                game.played(PlayerId::P1).to_vec(),
+                game.played(PlayerId::P2).to_vec(),
+            ],
+        }
+    }
+
+    pub fn opponent(&self) -> PlayerId {
+        self.player.opponent()
+    }
+
+    /// Returns the number of turns left to play, including the current one.
+    pub fn turns_left(&self) -> u32 {
+        (TURN_COUNT + 1).saturating_sub(self.turn)
+    }
+
+    /// Returns the card in the player's hand with the given ID.
+    pub fn card(&self, id: CardID) -> Option<&Card> {
+        self.hand.iter().find(|c| c.id() == id)
+    }
+
+    /// Returns every move the player can make this turn, as found by [`moves::legal_moves`].
+    pub fn legal_moves(&self) -> Vec<PlayerMove> {
+        moves::legal_moves(
+            &self.board,
+            &self.hand,
+            self.player,
+            self.special_points[self.player as usize],
+        )
+    }
+
+    /// Returns the cards in the player's deck that the viewer can't see: what is left of the
+    /// viewer's own draw pile, or everything the opponent hasn't played yet (their hand and draw
+    /// pile), in no particular order.
+    pub fn hidden_cards(&self, player: PlayerId) -> Vec<Card> {
+        let i = player as usize;
+
+        self.decks[i]
+            .iter()
+            .filter(|card| {
+                !self.played[i].contains(&card.id())
+                    && (player != self.player || self.card(card.id()).is_none())
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{cards::CardCatalog, stages::StageCatalog};
+
+    /// A match on the first standard stage where neither player has redrawn yet.
+    pub(super) fn game(seed: u64) -> GameState {
+        let cards = CardCatalog::standard();
+        let stage = StageCatalog::standard().get(0).unwrap().clone();
+        let deck =
+            |ids: std::ops::Range<CardID>| ids.map(|id| cards.get(id).unwrap().clone()).collect();
+
+        GameState::new(stage.board().clone(), [deck(1..16), deck(10..25)], seed)
+    }
+
+    /// Plays a match between two bots until it is over or `turns` turns have been played,
+    /// checking that every action they choose is legal.
+    pub(super) fn play(game: &mut GameState, bots: &mut [&mut dyn Bot; 2], turns: u32) {
+        while !game.is_finished() && game.played(PlayerId::P1).len() < turns as usize {
+            for player in [PlayerId::P1, PlayerId::P2] {
+                if game.is_waiting_for(player) {
+                    let action = bots[player as usize].choose_move(&PlayerView::new(game, player));
+                    assert_eq!(action.player(), player);
+                    game.apply(action).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_view() {
+        let mut game = game(3);
+        for player in [PlayerId::P1, PlayerId::P2] {
+            game.apply(Action::Redraw {
+                player,
+                redraw: false,
+            })
+            .unwrap();
+        }
+
+        let view = PlayerView::new(&game, PlayerId::P2);
+        assert_eq!(view.opponent(), PlayerId::P1);
+        assert_eq!(view.turns_left(), TURN_COUNT);
+        assert!(view.started);
+        assert_eq!(view.hand.len(), game.hand(PlayerId::P2).len());
+        assert_eq!(view.decks[0].len(), 15);
+        assert!(view.played[0].is_empty());
+
+        // P2 can see their own hand, but not P1's
+        assert_eq!(view.hidden_cards(PlayerId::P2).len(), view.draw_pile_len);
+        assert_eq!(view.hidden_cards(PlayerId::P1).len(), 15);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut game = game(3);
+        for player in [PlayerId::P1, PlayerId::P2] {
+            game.apply(Action::Redraw {
+                player,
+                redraw: false,
+            })
+            .unwrap();
+        }
+
+        let view = PlayerView::new(&game, PlayerId::P1);
+        let moves = view.legal_moves();
+
+        // No special points yet, so no special attacks
+        assert!(moves
+            .iter()
+            .all(|mv| !matches!(mv, PlayerMove::Place { special: true, .. })));
+        assert_eq!(
+            moves
+                .iter()
+                .filter(|mv| matches!(mv, PlayerMove::Pass { .. }))
+                .count(),
+            4
+        );
+        assert!(moves.len() > 4);
+
+        for mv in moves {
+            let mut game = game.clone();
+            game.apply(Action::Play {
+                player: PlayerId::P1,
+                mv,
+            })
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    game::{to_move, Action, PlayerMove},
+    protocol::PlayerId,
+    turn::{resolve_turn, Move},
+};
+
+use super::{Bot, PlayerView};
+
+/// A bot that makes whichever move gains it the most right now, without thinking ahead.
+///
+/// Each move is scored by the number of tiles it inks (counting tiles taken from the opponent
+/// twice, since the opponent loses them) plus the special points it gains, minus the points it
+/// spends. Special points are worth nothing on the last turn, since there is nothing left to
+/// spend them on. The opponent's move is not considered at all.
+///
+/// It always keeps its opening hand.
+#[derive(Debug, Default, Clone)]
+pub struct GreedyBot;
+
+impl GreedyBot {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Bot for GreedyBot {
+    fn choose_move(&mut self, view: &PlayerView) -> Action {
+        let player = view.player;
+
+        if !view.started {
+            return Action::Redraw {
+                player,
+                redraw: false,
+            };
+        }
+
+        // Ties go to the first move found, so the bot is deterministic
+        let mut best: Option<(i32, PlayerMove)> = None;
+
+        for mv in view.legal_moves() {
+            let score = score(view, &mv);
+
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, mv));
+            }
+        }
+
+        let mv = best.map_or_else(
+            || PlayerMove::Pass {
+                card: view.hand[0].id(),
+            },
+            |(_, mv)| mv,
+        );
+
+        Action::Play { player, mv }
+    }
+}
+
+/// How much the move gains the player, as described on [`GreedyBot`].
+pub(super) fn score(view: &PlayerView, mv: &PlayerMove) -> i32 {
+    let player = view.player;
+    let point_value = if view.turns_left() > 1 { 1 } else { 0 };
+
+    let Some(card) = view.card(mv.card()) else {
+        return i32::MIN;
+    };
+
+    let PlayerMove::Place { special, .. } = *mv else {
+        // Passing gains a point, and it's best to throw away the smallest card
+        return point_value * 2 - card.square_count() as i32;
+    };
+
+    let mv = to_move(mv, card);
+    let moves = match player {
+        PlayerId::P1 => [mv, Move::Pass],
+        PlayerId::P2 => [Move::Pass, mv],
+    };
+    let Ok(result) = resolve_turn(&view.board, &moves[0], &moves[1]) else {
+        return i32::MIN;
+    };
+
+    let opponent = view.opponent();
+    let inked = result.board.ink_count(player) as i32 - view.board.ink_count(player) as i32;
+    let taken = view.board.ink_count(opponent) as i32 - result.board.ink_count(opponent) as i32;
+
+    let activated = result
+        .board
+        .special_tiles()
+        .filter(|&(coord, owner)| {
+            owner == player && !view.activated.contains(&coord) && result.board.is_surrounded(coord)
+        })
+        .count() as i32;
+    let spent = if special { card.cost() as i32 } else { 0 };
+
+    // Scaled so that a pass, which gains one point, loses to any placement that inks a tile
+    2 * (inked + taken + point_value * (activated - spent))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ai::test::{game, play},
+        game::GameState,
+    };
+
+    /// Always passes with the first card in its hand.
+    struct PassBot;
+
+    impl Bot for PassBot {
+        fn choose_move(&mut self, view: &PlayerView) -> Action {
+            if !view.started {
+                return Action::Redraw {
+                    player: view.player,
+                    redraw: false,
+                };
+            }
+
+            Action::Play {
+                player: view.player,
+                mv: PlayerMove::Pass {
+                    card: view.hand[0].id(),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_greedy_beats_passing() {
+        let mut game: GameState = game(5);
+        play(&mut game, &mut [&mut GreedyBot, &mut PassBot], 4);
+
+        let scores = game.scores();
+        assert!(scores[0] > scores[1], "{scores:?}");
+    }
+
+    #[test]
+    fn test_greedy_is_deterministic() {
+        let mut a = game(9);
+        let mut b = game(9);
+        play(&mut a, &mut [&mut GreedyBot, &mut GreedyBot::new()], 4);
+        play(&mut b, &mut [&mut GreedyBot, &mut GreedyBot::new()], 4);
+
+        assert_eq!(a.board(), b.board());
+        assert_eq!(a.played(PlayerId::P1), b.played(PlayerId::P1));
+    }
+
+    #[test]
+    fn test_greedy_places_biggest_card() {
+        let mut game = game(2);
+        for player in [PlayerId::P1, PlayerId::P2] {
+            game.apply(Action::Redraw {
+                player,
+                redraw: false,
+            })
+            .unwrap();
+        }
+
+        let view = PlayerView::new(&game, PlayerId::P1);
+        let Action::Play {
+            mv: PlayerMove::Place { card, .. },
+            ..
+        } = GreedyBot.choose_move(&view)
+        else {
+            panic!("greedy bot should place a card on the first turn");
+        };
+
+        let biggest = view.hand.iter().map(|c| c.square_count()).max().unwrap();
+        assert_eq!(view.card(card).unwrap().square_count(), biggest);
+    }
+}
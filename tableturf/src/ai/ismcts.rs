@@ -0,0 +1,454 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    cards::CardID,
+    game::{Action, GameParts, GameState, PlayerMove, HAND_SIZE},
+    moves::MoveFinder,
+    protocol::PlayerId,
+    rng::Rng,
+};
+
+use super::{greedy, Bot, PlayerView};
+
+/// How much thinking the bot does for each move.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Budget {
+    /// Runs this many iterations of the search. The bot always makes the same moves for the same
+    /// seed.
+    Iterations(u32),
+    /// Searches for this long. How many iterations fit depends on the machine, so the bot's
+    /// moves can change from run to run.
+    Time(Duration),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IsmctsConfig {
+    pub budget: Budget,
+    /// How strongly the search favours moves it hasn't tried much over the best ones so far.
+    pub exploration: f64,
+    /// How many placements of each card in hand the search considers, picked by the same score
+    /// as [`GreedyBot`](super::GreedyBot). Passing with each card is always considered too.
+    pub placements_per_card: usize,
+    pub seed: u64,
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> Self {
+        Self {
+            budget: Budget::Iterations(1000),
+            exploration: 0.7,
+            placements_per_card: 3,
+            seed: 0,
+        }
+    }
+}
+
+/// A bot that plays out many possible futures of the match and picks the move that wins the
+/// most of them, using information set Monte Carlo tree search.
+///
+/// Since the bot can't see the opponent's hand or the order of either draw pile, every iteration
+/// of the search starts by dealing the hidden cards at random: the opponent is given a hand from
+/// the cards they haven't played yet, and both draw piles are shuffled. Both players choose their
+/// moves at the same time, so each node of the tree keeps separate statistics for each player's
+/// moves and each player picks theirs without knowing the other's. New positions are scored by
+/// playing the rest of the match out with random moves.
+///
+/// Only the most promising placements of each card are searched (see
+/// [`IsmctsConfig::placements_per_card`]), since there are far too many to try them all.
+///
+/// It always keeps its opening hand.
+#[derive(Debug, Clone)]
+pub struct IsmctsBot {
+    config: IsmctsConfig,
+    rng: Rng,
+}
+
+impl IsmctsBot {
+    pub fn new(config: IsmctsConfig) -> Self {
+        Self {
+            config,
+            rng: Rng::new(config.seed),
+        }
+    }
+
+    pub fn config(&self) -> &IsmctsConfig {
+        &self.config
+    }
+
+    /// Deals the cards the player can't see at random.
+    fn determinize(&mut self, view: &PlayerView) -> GameState {
+        let me = view.player as usize;
+        let opponent = view.opponent() as usize;
+
+        let mut own_pile = view.hidden_cards(view.player);
+        let mut opponent_cards = view.hidden_cards(view.opponent());
+        self.rng.shuffle(&mut own_pile);
+        self.rng.shuffle(&mut opponent_cards);
+
+        let opponent_pile = opponent_cards.split_off(HAND_SIZE.min(opponent_cards.len()));
+
+        let mut hands = [Vec::new(), Vec::new()];
+        let mut draw_piles = [Vec::new(), Vec::new()];
+        hands[me] = view.hand.clone();
+        hands[opponent] = opponent_cards;
+        draw_piles[me] = own_pile;
+        draw_piles[opponent] = opponent_pile;
+
+        GameState::from_parts(GameParts {
+            board: view.board.clone(),
+            turn: view.turn,
+            hands,
+            draw_piles,
+            decks: view.decks.clone(),
+            played: view.played.clone(),
+            special_points: view.special_points,
+            activated: view.activated.clone(),
+        })
+    }
+
+    /// Runs one iteration of the search from `node`, returning each player's reward.
+    fn iterate(&mut self, node: &mut Node, mut game: GameState) -> [f64; 2] {
+        if game.is_finished() {
+            return rewards(&game);
+        }
+
+        let players = [PlayerId::P1, PlayerId::P2];
+        let candidates = players.map(|p| node.candidates(&game, p, &self.config));
+
+        for (stats, moves) in node.stats.iter_mut().zip(&candidates) {
+            for mv in moves {
+                stats_mut(stats, *mv).available += 1;
+            }
+        }
+
+        let joint = [0, 1].map(|i| self.select(&node.stats[i], &candidates[i]));
+
+        for (player, mv) in players.into_iter().zip(joint) {
+            game.apply(Action::Play { player, mv })
+                .expect("candidate moves are legal");
+        }
+
+        let rewards = match node.children.iter_mut().find(|(moves, _)| *moves == joint) {
+            Some((_, child)) => self.iterate(child, game),
+            None => {
+                node.children.push((joint, Node::default()));
+                self.rollout(game)
+            }
+        };
+
+        for ((stats, mv), reward) in node.stats.iter_mut().zip(joint).zip(rewards) {
+            let stats = stats_mut(stats, mv);
+            stats.visits += 1;
+            stats.reward += reward;
+        }
+
+        rewards
+    }
+
+    /// Picks one player's move at a node: any move that hasn't been tried yet, otherwise the one
+    /// with the best upper confidence bound.
+    fn select(&mut self, stats: &[(PlayerMove, Stats)], moves: &[PlayerMove]) -> PlayerMove {
+        let get = |mv: &PlayerMove| {
+            stats
+                .iter()
+                .find(|(m, _)| m == mv)
+                .map_or_else(Stats::default, |&(_, s)| s)
+        };
+
+        let untried: Vec<_> = moves.iter().filter(|mv| get(mv).visits == 0).collect();
+        if !untried.is_empty() {
+            return *untried[self.rng.below(untried.len())];
+        }
+
+        // Ties go to the first move, so the search is deterministic
+        let mut best: Option<(f64, PlayerMove)> = None;
+        for mv in moves {
+            let stats = get(mv);
+            let visits = stats.visits as f64;
+            let value = stats.reward / visits
+                + self.config.exploration * ((stats.available as f64).ln() / visits).sqrt();
+
+            if best.is_none_or(|(best, _)| value > best) {
+                best = Some((value, *mv));
+            }
+        }
+
+        best.expect("there is always a move").1
+    }
+
+    /// Plays the rest of the match out with random moves.
+    fn rollout(&mut self, mut game: GameState) -> [f64; 2] {
+        while !game.is_finished() {
+            for player in [PlayerId::P1, PlayerId::P2] {
+                let mv = self.random_move(&game, player);
+                game.apply(Action::Play { player, mv })
+                    .expect("random moves are legal");
+            }
+        }
+
+        rewards(&game)
+    }
+
+    /// Places a random card somewhere random, or passes if no card fits anywhere.
+    fn random_move(&mut self, game: &GameState, player: PlayerId) -> PlayerMove {
+        let finder = MoveFinder::new(game.board(), player);
+        let mut hand: Vec<_> = game.hand(player).iter().collect();
+        self.rng.shuffle(&mut hand);
+
+        for card in &hand {
+            let moves = finder.placements(card, false);
+            if !moves.is_empty() {
+                return moves[self.rng.below(moves.len())];
+            }
+        }
+
+        PlayerMove::Pass { card: hand[0].id() }
+    }
+}
+
+impl Bot for IsmctsBot {
+    fn choose_move(&mut self, view: &PlayerView) -> Action {
+        let player = view.player;
+
+        if !view.started {
+            return Action::Redraw {
+                player,
+                redraw: false,
+            };
+        }
+
+        let mut root = Node::default();
+        let start = Instant::now();
+        let mut iterations = 0;
+
+        loop {
+            let done = match self.config.budget {
+                Budget::Iterations(n) => iterations >= n,
+                // Always search at least once, so there is a move to make
+                Budget::Time(limit) => iterations > 0 && start.elapsed() >= limit,
+            };
+            if done {
+                break;
+            }
+
+            let game = self.determinize(view);
+            self.iterate(&mut root, game);
+            iterations += 1;
+        }
+
+        // The bot's own hand is the same in every determinization, so every move it has tried is
+        // legal. Ties go to the first move tried.
+        let mut best: Option<(u32, PlayerMove)> = None;
+        for &(mv, stats) in &root.stats[player as usize] {
+            if best.is_none_or(|(visits, _)| stats.visits > visits) {
+                best = Some((stats.visits, mv));
+            }
+        }
+
+        let mv = best.map_or_else(
+            || PlayerMove::Pass {
+                card: view.hand[0].id(),
+            },
+            |(_, mv)| mv,
+        );
+
+        Action::Play { player, mv }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Stats {
+    /// How many times the move has been chosen.
+    visits: u32,
+    /// How many times the move could have been chosen, which differs from the number of times
+    /// the node was visited since each determinization deals a different hand.
+    available: u32,
+    /// The total reward from every time the move was chosen.
+    reward: f64,
+}
+
+/// A point in the search tree, reached by a sequence of moves from both players.
+///
+/// The board and special points at a node are the same in every determinization, since the moves
+/// leading to it are; only the hands and draw piles differ.
+#[derive(Debug, Default)]
+struct Node {
+    /// Each player's moves that have been considered from here.
+    stats: [Vec<(PlayerMove, Stats)>; 2],
+    /// The moves worth considering with each card a player has been dealt here.
+    candidates: Vec<(PlayerId, CardID, Vec<PlayerMove>)>,
+    children: Vec<([PlayerMove; 2], Node)>,
+}
+
+impl Node {
+    /// Returns the moves worth considering for the player with the hand they have in `game`: the
+    /// best few placements of each card, and passing with each card.
+    fn candidates(
+        &mut self,
+        game: &GameState,
+        player: PlayerId,
+        config: &IsmctsConfig,
+    ) -> Vec<PlayerMove> {
+        // Only needed when a card hasn't been seen at this node before
+        let mut search = None;
+        let mut moves = Vec::new();
+
+        for card in game.hand(player) {
+            let i = match self
+                .candidates
+                .iter()
+                .position(|(p, id, _)| *p == player && *id == card.id())
+            {
+                Some(i) => i,
+                None => {
+                    let (view, finder) = search.get_or_insert_with(|| {
+                        (
+                            PlayerView::new(game, player),
+                            MoveFinder::new(game.board(), player),
+                        )
+                    });
+
+                    let mut placed = finder.placements(card, false);
+                    if card.cost() <= view.special_points[player as usize] {
+                        placed.extend(finder.placements(card, true));
+                    }
+
+                    let mut scored: Vec<_> = placed
+                        .into_iter()
+                        .map(|mv| (greedy::score(view, &mv), mv))
+                        .collect();
+                    // Stable, so equally good moves stay in the order they were found
+                    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+                    let mut card_moves = vec![PlayerMove::Pass { card: card.id() }];
+                    card_moves.extend(
+                        scored
+                            .into_iter()
+                            .take(config.placements_per_card)
+                            .map(|(_, mv)| mv),
+                    );
+
+                    self.candidates.push((player, card.id(), card_moves));
+                    self.candidates.len() - 1
+                }
+            };
+
+            moves.extend_from_slice(&self.candidates[i].2);
+        }
+
+        moves
+    }
+}
+
+fn stats_mut(stats: &mut Vec<(PlayerMove, Stats)>, mv: PlayerMove) -> &mut Stats {
+    let i = match stats.iter().position(|(m, _)| *m == mv) {
+        Some(i) => i,
+        None => {
+            stats.push((mv, Stats::default()));
+            stats.len() - 1
+        }
+    };
+
+    &mut stats[i].1
+}
+
+/// Each player's reward for a finished match: 1 for a win, 0.5 for a draw and 0 for a loss.
+fn rewards(game: &GameState) -> [f64; 2] {
+    let [p1, p2] = game.scores();
+
+    match p1.cmp(&p2) {
+        std::cmp::Ordering::Greater => [1.0, 0.0],
+        std::cmp::Ordering::Less => [0.0, 1.0],
+        std::cmp::Ordering::Equal => [0.5, 0.5],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ai::{
+            test::{game, play},
+            GreedyBot,
+        },
+        game::TURN_COUNT,
+    };
+
+    fn started(seed: u64) -> GameState {
+        let mut game = game(seed);
+        for player in [PlayerId::P1, PlayerId::P2] {
+            game.apply(Action::Redraw {
+                player,
+                redraw: false,
+            })
+            .unwrap();
+        }
+        game
+    }
+
+    fn config(iterations: u32, seed: u64) -> IsmctsConfig {
+        IsmctsConfig {
+            budget: Budget::Iterations(iterations),
+            placements_per_card: 2,
+            seed,
+            ..IsmctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_ismcts_is_deterministic() {
+        let game = started(4);
+        let view = PlayerView::new(&game, PlayerId::P2);
+
+        let a = IsmctsBot::new(config(12, 11)).choose_move(&view);
+        let b = IsmctsBot::new(config(12, 11)).choose_move(&view);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_determinize() {
+        let game = started(2);
+        let view = PlayerView::new(&game, PlayerId::P1);
+        let mut bot = IsmctsBot::new(config(1, 3));
+        let guess = bot.determinize(&view);
+
+        assert_eq!(guess.board(), game.board());
+        assert_eq!(guess.turn(), game.turn());
+        for player in [PlayerId::P1, PlayerId::P2] {
+            assert_eq!(guess.hand(player).len(), HAND_SIZE);
+            assert_eq!(guess.draw_pile_len(player), game.draw_pile_len(player));
+        }
+
+        // The bot's own hand is known, the opponent's is dealt from their deck
+        let ids = |cards: &[crate::cards::Card]| cards.iter().map(|c| c.id()).collect::<Vec<_>>();
+        assert_eq!(ids(guess.hand(PlayerId::P1)), ids(game.hand(PlayerId::P1)));
+        let deck = ids(game.deck(PlayerId::P2));
+        assert!(guess
+            .hand(PlayerId::P2)
+            .iter()
+            .all(|c| deck.contains(&c.id())));
+    }
+
+    #[test]
+    fn test_ismcts_plays_legal_match() {
+        let mut game = game(6);
+        let mut ismcts = IsmctsBot::new(config(3, 1));
+        play(&mut game, &mut [&mut ismcts, &mut GreedyBot], TURN_COUNT);
+
+        assert!(game.is_finished());
+        assert_eq!(game.played(PlayerId::P1).len(), TURN_COUNT as usize);
+    }
+
+    #[test]
+    fn test_ismcts_time_budget() {
+        let game = started(8);
+        let view = PlayerView::new(&game, PlayerId::P1);
+        let mut bot = IsmctsBot::new(IsmctsConfig {
+            budget: Budget::Time(Duration::ZERO),
+            ..IsmctsConfig::default()
+        });
+
+        let mut game = game.clone();
+        game.apply(bot.choose_move(&view)).unwrap();
+    }
+}
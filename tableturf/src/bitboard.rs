@@ -0,0 +1,599 @@
+//! A packed representation of a board for fast simulation.
+//!
+//! [`Board`] keeps its tiles in a hash map, which is convenient but slow to copy and to look
+//! tiles up in. A [`BitBoard`] instead keeps one bit mask for each kind of tile: the stage itself,
+//! walls, conflict tiles, and each player's ink and special tiles. Masks are fixed size arrays, so
+//! copying a board is cheap and resolving a turn is a handful of bitwise operations.
+//!
+//! Each mask stores the board row by row with a column of padding on either side and a row of
+//! padding above and below, so that every tile's 8 neighbours have a bit even at the edges.
+
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Not},
+    sync::Arc,
+};
+
+use crate::{
+    board::{Board, Coord, PlacementError, Rotation, Tile},
+    cards::Card,
+    protocol::PlayerId,
+    turn::{InvalidMove, Move},
+};
+
+const WORDS: usize = 16;
+
+/// The number of bits in a mask, which limits how big a board can be, including the padding
+/// around it.
+pub const MAX_BITS: usize = WORDS * 64;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+struct Mask([u64; WORDS]);
+
+impl Mask {
+    const EMPTY: Mask = Mask([0; WORDS]);
+
+    fn get(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&w| w == 0)
+    }
+
+    fn count(&self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+}
+
+impl BitAnd for Mask {
+    type Output = Mask;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a &= b;
+        }
+        self
+    }
+}
+
+impl BitOr for Mask {
+    type Output = Mask;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a |= b;
+        }
+        self
+    }
+}
+
+impl BitXor for Mask {
+    type Output = Mask;
+
+    fn bitxor(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a ^= b;
+        }
+        self
+    }
+}
+
+impl Not for Mask {
+    type Output = Mask;
+
+    fn not(mut self) -> Self::Output {
+        for a in &mut self.0 {
+            *a = !*a;
+        }
+        self
+    }
+}
+
+/// Returned when converting a board that is too big to fit in a [`BitBoard`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TooLarge {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} board is too large for a bitboard",
+            self.width, self.height
+        )
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+/// A board stored as bit masks, which can be converted to and from a [`Board`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitBoard {
+    name: Arc<str>,
+    width: i32,
+    height: i32,
+    /// Tiles that are part of the stage.
+    stage: Mask,
+    walls: Mask,
+    conflicts: Mask,
+    /// Each player's ink, including their special tiles.
+    ink: [Mask; 2],
+    special: [Mask; 2],
+}
+
+impl TryFrom<&Board> for BitBoard {
+    type Error = TooLarge;
+
+    fn try_from(board: &Board) -> Result<Self, Self::Error> {
+        let (width, height) = (board.width(), board.height());
+
+        // Counted in usize so that absurd sizes can't overflow
+        let bits = usize::try_from(width)
+            .ok()
+            .zip(usize::try_from(height).ok())
+            .and_then(|(w, h)| (w + 2).checked_mul(h + 2));
+
+        if bits.is_none_or(|bits| bits > MAX_BITS) {
+            return Err(TooLarge { width, height });
+        }
+
+        let mut bitboard = BitBoard {
+            name: board.name().into(),
+            width,
+            height,
+            stage: Mask::EMPTY,
+            walls: Mask::EMPTY,
+            conflicts: Mask::EMPTY,
+            ink: [Mask::EMPTY; 2],
+            special: [Mask::EMPTY; 2],
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let coord = Coord::new(x, y);
+                let i = bitboard.index(coord);
+
+                match board.get(coord) {
+                    None => continue,
+                    Some(Tile::Empty) => {}
+                    Some(Tile::Wall) => bitboard.walls.set(i),
+                    Some(Tile::Conflict) => bitboard.conflicts.set(i),
+                    Some(Tile::Ink { owner, special }) => {
+                        bitboard.ink[owner as usize].set(i);
+                        if special {
+                            bitboard.special[owner as usize].set(i);
+                        }
+                    }
+                }
+
+                bitboard.stage.set(i);
+            }
+        }
+
+        Ok(bitboard)
+    }
+}
+
+impl From<&BitBoard> for Board {
+    fn from(bitboard: &BitBoard) -> Self {
+        let mut board = Board::new(&*bitboard.name, bitboard.width, bitboard.height);
+
+        for y in 0..bitboard.height {
+            for x in 0..bitboard.width {
+                let coord = Coord::new(x, y);
+
+                match bitboard.get(coord) {
+                    Some(tile) => board.set(coord, tile),
+                    None => board.remove(coord),
+                }
+            }
+        }
+
+        board
+    }
+}
+
+impl BitBoard {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn stride(&self) -> usize {
+        self.width as usize + 2
+    }
+
+    /// Returns the bit for a coordinate within the board's bounding rectangle.
+    fn index(&self, coord: Coord) -> usize {
+        (coord.y as usize + 1) * self.stride() + coord.x as usize + 1
+    }
+
+    /// Returns the bit for a coordinate, or None if it is outside the bounding rectangle.
+    fn checked_index(&self, coord: Coord) -> Option<usize> {
+        ((0..self.width).contains(&coord.x) && (0..self.height).contains(&coord.y))
+            .then(|| self.index(coord))
+    }
+
+    /// Returns the bits of the 8 tiles around a bit. The padding around the board means these
+    /// are always in range.
+    fn neighbours(&self, i: usize) -> [usize; 8] {
+        let s = self.stride();
+        [
+            i - s - 1,
+            i - s,
+            i - s + 1,
+            i - 1,
+            i + 1,
+            i + s - 1,
+            i + s,
+            i + s + 1,
+        ]
+    }
+
+    /// Returns the tile at the given coordinate, or None if it is not part of the stage.
+    pub fn get(&self, coord: Coord) -> Option<Tile> {
+        let i = self.checked_index(coord)?;
+
+        if !self.stage.get(i) {
+            None
+        } else if self.walls.get(i) {
+            Some(Tile::Wall)
+        } else if self.conflicts.get(i) {
+            Some(Tile::Conflict)
+        } else if let Some(owner) = [PlayerId::P1, PlayerId::P2]
+            .into_iter()
+            .find(|&p| self.ink[p as usize].get(i))
+        {
+            Some(Tile::Ink {
+                owner,
+                special: self.special[owner as usize].get(i),
+            })
+        } else {
+            Some(Tile::Empty)
+        }
+    }
+
+    /// Tiles that are part of the stage and have nothing on them.
+    fn empty(&self) -> Mask {
+        self.stage & !(self.walls | self.conflicts | self.ink[0] | self.ink[1])
+    }
+
+    /// The same as [`Board::check_placement`].
+    pub fn check_placement(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+    ) -> Result<(), PlacementError> {
+        self.check(card, rotation, anchor, player, false)
+            .map(|_| ())
+    }
+
+    /// The same as [`Board::check_special_placement`].
+    pub fn check_special_placement(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+    ) -> Result<(), PlacementError> {
+        self.check(card, rotation, anchor, player, true).map(|_| ())
+    }
+
+    /// Checks a placement, returning the masks of the tiles it covers and of its special squares.
+    ///
+    /// Squares are checked in the same order as [`Board`] checks them, so the errors match too.
+    fn check(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+        special_attack: bool,
+    ) -> Result<(Mask, Mask), PlacementError> {
+        let p = player as usize;
+        let connects_to = if special_attack {
+            &self.special[p]
+        } else {
+            &self.ink[p]
+        };
+
+        let mut squares = Mask::EMPTY;
+        let mut specials = Mask::EMPTY;
+        let mut connected = false;
+
+        for (offset, special) in card.footprint(rotation).offsets() {
+            let coord = anchor + offset;
+            let Some(i) = self.checked_index(coord).filter(|&i| self.stage.get(i)) else {
+                return Err(PlacementError::OutOfBounds(coord));
+            };
+
+            if self.walls.get(i) {
+                return Err(PlacementError::Wall(coord));
+            }
+
+            let inked = self.ink[0].get(i) || self.ink[1].get(i);
+            if special_attack && (self.special[0].get(i) || self.special[1].get(i)) {
+                return Err(PlacementError::SpecialTile(coord));
+            }
+            if self.conflicts.get(i) || (inked && !special_attack) {
+                return Err(PlacementError::Occupied(coord));
+            }
+
+            squares.set(i);
+            if special {
+                specials.set(i);
+            }
+
+            connected = connected || self.neighbours(i).iter().any(|&n| connects_to.get(n));
+        }
+
+        if squares.is_empty() {
+            Err(PlacementError::EmptyCard)
+        } else if !connected {
+            Err(PlacementError::NotConnected)
+        } else {
+            Ok((squares, specials))
+        }
+    }
+
+    /// The same as [`Board::is_surrounded`].
+    pub fn is_surrounded(&self, coord: Coord) -> bool {
+        let Some(i) = self.checked_index(coord) else {
+            return coord
+                .neighbours()
+                .all(|n| !matches!(self.get(n), Some(Tile::Empty)));
+        };
+        let empty = self.empty();
+
+        !self.neighbours(i).iter().any(|&n| empty.get(n))
+    }
+
+    /// Returns the number of tiles inked by the given player, which is their score.
+    pub fn ink_count(&self, player: PlayerId) -> u32 {
+        self.ink[player as usize].count()
+    }
+
+    /// Returns the number of special tiles the player has.
+    pub fn special_count(&self, player: PlayerId) -> u32 {
+        self.special[player as usize].count()
+    }
+}
+
+/// The same as [`crate::turn::resolve_turn`], but on a bitboard, and only returning the new
+/// board.
+pub fn resolve_turn(board: &BitBoard, p1: &Move, p2: &Move) -> Result<BitBoard, InvalidMove> {
+    let squares = |mv: &Move, player| match mv {
+        Move::Pass => Ok((Mask::EMPTY, Mask::EMPTY)),
+        Move::Place(p) => board
+            .check(p.card, p.rotation, p.anchor, player, p.special)
+            .map_err(|error| InvalidMove { player, error }),
+    };
+    let (squares1, specials1) = squares(p1, PlayerId::P1)?;
+    let (squares2, specials2) = squares(p2, PlayerId::P2)?;
+
+    // Where the cards overlap, a special square beats a normal one, then the smaller card wins
+    let overlap = squares1 & squares2;
+    let same_kind = !(specials1 ^ specials2);
+    let (count1, count2) = (squares1.count(), squares2.count());
+    let smaller = |wins: bool| if wins { same_kind } else { Mask::EMPTY };
+
+    let won1 = overlap & ((specials1 & !specials2) | smaller(count1 < count2));
+    let won2 = overlap & ((specials2 & !specials1) | smaller(count2 < count1));
+    let conflicts = overlap & smaller(count1 == count2);
+
+    let ink1 = (squares1 & !squares2) | won1;
+    let ink2 = (squares2 & !squares1) | won2;
+    let placed = squares1 | squares2;
+
+    let mut new_board = board.clone();
+    new_board.conflicts = (board.conflicts & !placed) | conflicts;
+    for (p, (ink, specials)) in [(ink1, specials1), (ink2, specials2)]
+        .into_iter()
+        .enumerate()
+    {
+        new_board.ink[p] = (board.ink[p] & !placed) | ink;
+        new_board.special[p] = (board.special[p] & !placed) | (ink & specials);
+    }
+
+    Ok(new_board)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cards::{test_card, CardCatalog},
+        moves::legal_moves,
+        rng::Rng,
+        stages::StageCatalog,
+        turn,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        for stage in StageCatalog::standard().stages() {
+            let bitboard = BitBoard::try_from(stage.board()).unwrap();
+            assert_eq!(bitboard.name(), stage.name());
+            assert_eq!(&Board::from(&bitboard), stage.board());
+
+            for player in [PlayerId::P1, PlayerId::P2] {
+                assert_eq!(bitboard.ink_count(player), 1);
+                assert_eq!(bitboard.special_count(player), 1);
+            }
+        }
+
+        let board = Board::new("Huge", 40, 40);
+        assert_eq!(
+            BitBoard::try_from(&board),
+            Err(TooLarge {
+                width: 40,
+                height: 40
+            })
+        );
+
+        let board = Board::from_tiles("Absurd", i32::MAX, i32::MAX, Default::default());
+        assert_eq!(
+            BitBoard::try_from(&board),
+            Err(TooLarge {
+                width: i32::MAX,
+                height: i32::MAX
+            })
+        );
+    }
+
+    /// Plays random moves on a standard stage with both kinds of board, checking they agree on
+    /// the outcome of every turn and on whether every move is legal.
+    #[test]
+    fn test_matches_board() {
+        let cards: Vec<Card> = CardCatalog::standard().cards().cloned().collect();
+        let stages = StageCatalog::standard();
+        let stage = stages.get(1).unwrap();
+        let mut rng = Rng::new(42);
+        let mut board = stage.board().clone();
+
+        for _ in 0..12 {
+            let bitboard = BitBoard::try_from(&board).unwrap();
+            let hand: Vec<Card> = (0..4)
+                .map(|_| cards[rng.below(cards.len())].clone())
+                .collect();
+
+            // Legality, including the reason moves are illegal
+            for card in &hand {
+                for rotation in Rotation::ALL {
+                    for y in -1..=board.height() {
+                        for x in -1..=board.width() {
+                            let anchor = Coord::new(x, y);
+                            assert_eq!(
+                                bitboard.check_placement(card, rotation, anchor, PlayerId::P1),
+                                board.check_placement(card, rotation, anchor, PlayerId::P1)
+                            );
+                            assert_eq!(
+                                bitboard.check_special_placement(
+                                    card,
+                                    rotation,
+                                    anchor,
+                                    PlayerId::P2
+                                ),
+                                board.check_special_placement(card, rotation, anchor, PlayerId::P2)
+                            );
+                        }
+                    }
+                }
+            }
+
+            let mut choose = |player| {
+                let moves = legal_moves(&board, &hand, player, 3);
+                let mv = moves[rng.below(moves.len())];
+                let card = hand.iter().find(|c| c.id() == mv.card()).unwrap();
+                crate::game::to_move(&mv, card)
+            };
+            let (p1, p2) = (choose(PlayerId::P1), choose(PlayerId::P2));
+
+            let expected = turn::resolve_turn(&board, &p1, &p2).unwrap().board;
+            let resolved = resolve_turn(&bitboard, &p1, &p2).unwrap();
+            assert_eq!(Board::from(&resolved), expected);
+
+            for coord in
+                (0..board.height()).flat_map(|y| (0..board.width()).map(move |x| Coord::new(x, y)))
+            {
+                assert_eq!(resolved.is_surrounded(coord), expected.is_surrounded(coord));
+            }
+
+            board = expected;
+        }
+
+        assert!(board.ink_count(PlayerId::P1) > 1);
+    }
+
+    #[test]
+    fn test_overlap() {
+        // P1 on the left and P2 on the right, so both can reach the middle column
+        let mut board = Board::new("Test board", 7, 3);
+        for y in 0..3 {
+            board.set(
+                Coord::new(0, y),
+                Tile::Ink {
+                    owner: PlayerId::P1,
+                    special: false,
+                },
+            );
+            board.set(
+                Coord::new(6, y),
+                Tile::Ink {
+                    owner: PlayerId::P2,
+                    special: false,
+                },
+            );
+        }
+        let bitboard = BitBoard::try_from(&board).unwrap();
+
+        /// Places the card so that the left of its bounding box is at `x`.
+        fn place(card: &Card, x: i32) -> Move<'_> {
+            Move::Place(turn::Placement {
+                card,
+                rotation: Rotation::Up,
+                anchor: Coord::new(x, 1) + card.footprint(Rotation::Up).pivot,
+                special: false,
+            })
+        }
+
+        let cases = [
+            (["###"], ["###"]),
+            (["##*"], ["*##"]),
+            (["##*"], ["###"]),
+            (["###"], ["*##"]),
+            (["###"], ["####"]),
+        ];
+
+        for (c1, c2) in cases {
+            let (c1, c2) = (test_card(&c1), test_card(&c2));
+            let (p1, p2) = (
+                place(&c1, 1),
+                place(&c2, 6 - c2.footprint(Rotation::Up).width),
+            );
+
+            let expected = turn::resolve_turn(&board, &p1, &p2).unwrap().board;
+            let resolved = resolve_turn(&bitboard, &p1, &p2).unwrap();
+            assert_eq!(Board::from(&resolved), expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_move() {
+        let cards = CardCatalog::standard();
+        let stages = StageCatalog::standard();
+        let stage = stages.get(0).unwrap();
+        let bitboard = BitBoard::try_from(stage.board()).unwrap();
+        let card = cards.get(1).unwrap();
+
+        let mv = Move::Place(turn::Placement {
+            card,
+            rotation: Rotation::Up,
+            anchor: Coord::new(0, 0),
+            special: false,
+        });
+
+        assert_eq!(
+            resolve_turn(&bitboard, &Move::Pass, &mv)
+                .unwrap_err()
+                .player,
+            PlayerId::P2
+        );
+    }
+}
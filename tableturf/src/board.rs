@@ -1,14 +1,50 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+use crate::{cards::Card, protocol::PlayerId};
+
+#[derive(
+    Debug, Copy, Clone, Default, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub struct Coord {
     pub x: i32,
     pub y: i32,
 }
 
+impl Coord {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Rotates the coordinate clockwise around the origin.
+    pub fn rotated(self, rotation: Rotation) -> Coord {
+        match rotation {
+            Rotation::Up => self,
+            Rotation::Right => Coord::new(-self.y, self.x),
+            Rotation::Down => Coord::new(-self.x, -self.y),
+            Rotation::Left => Coord::new(self.y, -self.x),
+        }
+    }
+
+    /// Returns the 8 coordinates surrounding this one, orthogonally and diagonally.
+    pub fn neighbours(self) -> impl Iterator<Item = Coord> {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&d| d != (0, 0))
+            .map(move |(dx, dy)| Coord::new(self.x + dx, self.y + dy))
+    }
+}
+
+impl std::ops::Add for Coord {
+    type Output = Coord;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Coord::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum Rotation {
@@ -18,17 +54,399 @@ pub enum Rotation {
     Left = 3,
 }
 
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::Up,
+        Rotation::Right,
+        Rotation::Down,
+        Rotation::Left,
+    ];
+
+    /// Returns the next rotation clockwise.
+    pub fn clockwise(self) -> Rotation {
+        Rotation::ALL[(self as usize + 1) % 4]
+    }
+
+    /// Returns the next rotation anticlockwise.
+    pub fn anticlockwise(self) -> Rotation {
+        Rotation::ALL[(self as usize + 3) % 4]
+    }
+}
+
 #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Tile {
     Empty,
     Wall,
-    Tile { special: bool },
+    Ink {
+        owner: PlayerId,
+        special: bool,
+    },
+    /// A neutral tile created when both players ink the same square in the same turn. It belongs
+    /// to nobody and blocks placement like a wall.
+    Conflict,
 }
 
-#[derive(Debug)]
+/// The reason a card could not be placed on the board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlacementError {
+    /// A square of the card would be placed off the board.
+    OutOfBounds(Coord),
+    /// A square of the card would be placed on top of a wall.
+    Wall(Coord),
+    /// A square of the card would be placed on top of a tile that has already been inked.
+    Occupied(Coord),
+    /// A special attack would be placed on top of a special tile.
+    SpecialTile(Coord),
+    /// None of the card's squares touch one of the player's own tiles (or, for a special attack,
+    /// one of the player's own special tiles).
+    NotConnected,
+    /// The player does not have enough special points to use the card as a special attack.
+    NotEnoughSpecial { cost: u32, points: u32 },
+    /// The card has no inked squares at all.
+    EmptyCard,
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::OutOfBounds(c) => {
+                write!(f, "square ({}, {}) is out of bounds", c.x, c.y)
+            }
+            PlacementError::Wall(c) => write!(f, "square ({}, {}) is on a wall", c.x, c.y),
+            PlacementError::Occupied(c) => write!(f, "square ({}, {}) is already inked", c.x, c.y),
+            PlacementError::SpecialTile(c) => {
+                write!(f, "square ({}, {}) is a special tile", c.x, c.y)
+            }
+            PlacementError::NotConnected => {
+                write!(f, "card does not touch any of the player's tiles")
+            }
+            PlacementError::NotEnoughSpecial { cost, points } => {
+                write!(
+                    f,
+                    "special attack costs {cost} points but player has {points}"
+                )
+            }
+            PlacementError::EmptyCard => write!(f, "card has no squares"),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+/// A tableturf board.
+///
+/// Coordinates inside the `width` x `height` rectangle that are not in the tile map are not part
+/// of the stage, which is how irregularly shaped stages are represented.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     width: i32,
     height: i32,
     name: String,
     board: HashMap<Coord, Tile>,
 }
+
+impl Board {
+    /// Creates a new rectangular board where every tile is empty.
+    pub fn new(name: impl Into<String>, width: i32, height: i32) -> Self {
+        let board = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (Coord::new(x, y), Tile::Empty)))
+            .collect();
+
+        Self {
+            width,
+            height,
+            name: name.into(),
+            board,
+        }
+    }
+
+    /// Creates a board from a map of tiles. Coordinates not in the map are not part of the
+    /// stage.
+    pub(crate) fn from_tiles(
+        name: impl Into<String>,
+        width: i32,
+        height: i32,
+        board: HashMap<Coord, Tile>,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            name: name.into(),
+            board,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Returns the tile at the given coordinate, or None if it is not part of the stage.
+    pub fn get(&self, coord: Coord) -> Option<Tile> {
+        self.board.get(&coord).copied()
+    }
+
+    /// Sets the tile at the given coordinate. Coordinates outside the board's bounding rectangle
+    /// are ignored.
+    pub fn set(&mut self, coord: Coord, tile: Tile) {
+        if (0..self.width).contains(&coord.x) && (0..self.height).contains(&coord.y) {
+            self.board.insert(coord, tile);
+        }
+    }
+
+    /// Removes the tile at the given coordinate from the stage entirely.
+    pub fn remove(&mut self, coord: Coord) {
+        self.board.remove(&coord);
+    }
+
+    /// Checks whether the given player may place a card with the given rotation so that its
+    /// pivot square (see [`Card::footprint`]) is at `anchor`.
+    ///
+    /// A placement is legal if every square of the card lands on an empty tile of the stage and
+    /// at least one square touches (orthogonally or diagonally) a tile inked by the player.
+    pub fn check_placement(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+    ) -> Result<(), PlacementError> {
+        self.check(card, rotation, anchor, player, false)
+    }
+
+    /// Checks whether the given player may place a card as a special attack.
+    ///
+    /// Special attacks may be placed on top of either player's normal ink, but not on walls,
+    /// conflict tiles or special tiles, and must touch one of the player's own special tiles.
+    /// This does not check whether the player has enough special points.
+    pub fn check_special_placement(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+    ) -> Result<(), PlacementError> {
+        self.check(card, rotation, anchor, player, true)
+    }
+
+    fn check(
+        &self,
+        card: &Card,
+        rotation: Rotation,
+        anchor: Coord,
+        player: PlayerId,
+        special_attack: bool,
+    ) -> Result<(), PlacementError> {
+        let mut any_squares = false;
+        let mut connected = false;
+
+        for (offset, _) in card.footprint(rotation).offsets() {
+            any_squares = true;
+            let coord = anchor + offset;
+
+            match self.get(coord) {
+                None => return Err(PlacementError::OutOfBounds(coord)),
+                Some(Tile::Wall) => return Err(PlacementError::Wall(coord)),
+                Some(Tile::Ink { special: true, .. }) if special_attack => {
+                    return Err(PlacementError::SpecialTile(coord))
+                }
+                Some(Tile::Ink { .. }) if special_attack => {}
+                Some(Tile::Ink { .. } | Tile::Conflict) => {
+                    return Err(PlacementError::Occupied(coord))
+                }
+                Some(Tile::Empty) => {}
+            }
+
+            connected = connected
+                || coord.neighbours().any(|n| match self.get(n) {
+                    Some(Tile::Ink { owner, special }) => {
+                        owner == player && (special || !special_attack)
+                    }
+                    _ => false,
+                });
+        }
+
+        if !any_squares {
+            Err(PlacementError::EmptyCard)
+        } else if !connected {
+            Err(PlacementError::NotConnected)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether all 8 tiles around the given coordinate are filled, i.e. none of them are
+    /// empty. Tiles that are not part of the stage count as filled.
+    pub fn is_surrounded(&self, coord: Coord) -> bool {
+        coord
+            .neighbours()
+            .all(|n| !matches!(self.get(n), Some(Tile::Empty)))
+    }
+
+    /// Returns the number of tiles inked by the given player, which is their score.
+    pub fn ink_count(&self, player: PlayerId) -> u32 {
+        self.board
+            .values()
+            .filter(|tile| matches!(tile, Tile::Ink { owner, .. } if *owner == player))
+            .count() as u32
+    }
+
+    /// Returns an iterator over every special tile on the board and its owner.
+    pub fn special_tiles(&self) -> impl Iterator<Item = (Coord, PlayerId)> + '_ {
+        self.board.iter().filter_map(|(&coord, tile)| match tile {
+            Tile::Ink {
+                owner,
+                special: true,
+            } => Some((coord, *owner)),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cards::test_card as card;
+
+    fn board() -> Board {
+        let mut board = Board::new("Test board", 5, 5);
+        board.set(
+            Coord::new(0, 0),
+            Tile::Ink {
+                owner: PlayerId::P1,
+                special: true,
+            },
+        );
+        board.set(
+            Coord::new(4, 4),
+            Tile::Ink {
+                owner: PlayerId::P2,
+                special: true,
+            },
+        );
+        board.set(Coord::new(2, 2), Tile::Wall);
+        board
+    }
+
+    #[test]
+    fn test_legal_placement() {
+        let card = card(&["##"]);
+
+        assert_eq!(
+            board().check_placement(&card, Rotation::Up, Coord::new(1, 0), PlayerId::P1),
+            Ok(())
+        );
+        // Diagonal contact is enough
+        assert_eq!(
+            board().check_placement(&card, Rotation::Up, Coord::new(1, 1), PlayerId::P1),
+            Ok(())
+        );
+        assert_eq!(
+            board().check_placement(&card, Rotation::Up, Coord::new(2, 3), PlayerId::P2),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_illegal_placement() {
+        let card = card(&["##"]);
+        let board = board();
+
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(4, 0), PlayerId::P1),
+            Err(PlacementError::OutOfBounds(Coord::new(5, 0)))
+        );
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(1, 2), PlayerId::P1),
+            Err(PlacementError::Wall(Coord::new(2, 2)))
+        );
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(0, 0), PlayerId::P1),
+            Err(PlacementError::Occupied(Coord::new(0, 0)))
+        );
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(1, 0), PlayerId::P2),
+            Err(PlacementError::NotConnected)
+        );
+    }
+
+    #[test]
+    fn test_rotated_placement() {
+        let card = card(&["###"]);
+        let board = board();
+
+        // The card rotates around its middle square, so pointing right it becomes a vertical
+        // line centered on the anchor
+        assert_eq!(
+            board.check_placement(&card, Rotation::Right, Coord::new(0, 2), PlayerId::P1),
+            Ok(())
+        );
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(0, 2), PlayerId::P1),
+            Err(PlacementError::OutOfBounds(Coord::new(-1, 2)))
+        );
+    }
+
+    #[test]
+    fn test_special_placement() {
+        let card = card(&["##"]);
+        let mut board = board();
+        board.set(
+            Coord::new(1, 0),
+            Tile::Ink {
+                owner: PlayerId::P2,
+                special: false,
+            },
+        );
+        board.set(
+            Coord::new(0, 1),
+            Tile::Ink {
+                owner: PlayerId::P1,
+                special: false,
+            },
+        );
+
+        // Special attacks can go over normal ink of either player
+        assert_eq!(
+            board.check_special_placement(&card, Rotation::Up, Coord::new(1, 0), PlayerId::P1),
+            Ok(())
+        );
+        assert_eq!(
+            board.check_special_placement(&card, Rotation::Up, Coord::new(0, 1), PlayerId::P1),
+            Ok(())
+        );
+        assert_eq!(
+            board.check_placement(&card, Rotation::Up, Coord::new(1, 0), PlayerId::P1),
+            Err(PlacementError::Occupied(Coord::new(1, 0)))
+        );
+        // ...but not over special tiles
+        assert_eq!(
+            board.check_special_placement(&card, Rotation::Up, Coord::new(3, 4), PlayerId::P2),
+            Err(PlacementError::SpecialTile(Coord::new(4, 4)))
+        );
+        // and they must touch one of the player's special tiles, not just any of their ink
+        assert_eq!(
+            board.check_special_placement(&card, Rotation::Up, Coord::new(0, 2), PlayerId::P1),
+            Err(PlacementError::NotConnected)
+        );
+    }
+
+    #[test]
+    fn test_is_surrounded() {
+        let mut board = board();
+        assert!(!board.is_surrounded(Coord::new(0, 0)));
+
+        for coord in [Coord::new(1, 0), Coord::new(0, 1), Coord::new(1, 1)] {
+            board.set(coord, Tile::Wall);
+        }
+        assert!(board.is_surrounded(Coord::new(0, 0)));
+    }
+}
@@ -1,17 +1,735 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
 
-use crate::board::Tile;
+use crate::board::{Coord, Rotation};
+
+pub type CardID = usize;
+
+/// The size of the grid that a card's pattern is drawn on.
+pub const CARD_SIZE: usize = 8;
 
-type CardID = usize;
+/// The default card list, embedded so that every binary agrees on what the cards are. The names
+/// are borrowed from the official game but the patterns are placeholders, not the official
+/// card set.
+const DEFAULT_CARDS: &str = include_str!("../data/default_cards.json");
+
+/// A single square of a card's pattern.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Square {
+    #[default]
+    Empty,
+    Ink,
+    Special,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "CardFields")]
 pub struct Card {
     id: CardID,
     name: String,
-    tiles: [[Tile; 8]; 8],
+    /// The number of special points needed to play this card as a special attack.
+    cost: u32,
+    /// The card's pattern, indexed as `tiles[y][x]`.
+    tiles: [[Square; CARD_SIZE]; CARD_SIZE],
+    /// The footprint in each rotation, indexed by [`Rotation`]. Placement checks look these up
+    /// constantly, so they are worked out once when the card is created.
+    #[serde(skip)]
+    footprints: [Footprint; 4],
+}
+
+/// The serialised fields of a [`Card`], which leave out the footprints since they are worked out
+/// from the pattern.
+#[derive(Deserialize)]
+struct CardFields {
+    id: CardID,
+    name: String,
+    cost: u32,
+    tiles: [[Square; CARD_SIZE]; CARD_SIZE],
+}
+
+impl From<CardFields> for Card {
+    fn from(fields: CardFields) -> Self {
+        Card::new(fields.id, fields.name, fields.cost, fields.tiles)
+    }
+}
+
+impl Card {
+    pub fn new(
+        id: CardID,
+        name: impl Into<String>,
+        cost: u32,
+        tiles: [[Square; CARD_SIZE]; CARD_SIZE],
+    ) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            cost,
+            tiles,
+            footprints: Rotation::ALL.map(|rotation| footprint(&tiles, rotation)),
+        }
+    }
+
+    pub fn id(&self) -> CardID {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// Returns the card's pattern, indexed as `tiles[y][x]`.
+    pub fn tiles(&self) -> &[[Square; CARD_SIZE]; CARD_SIZE] {
+        &self.tiles
+    }
+
+    /// Returns the number of inked squares (including special squares) on the card.
+    pub fn square_count(&self) -> usize {
+        self.tiles
+            .iter()
+            .flatten()
+            .filter(|&&s| s != Square::Empty)
+            .count()
+    }
+
+    /// Returns the squares of the card after applying the given rotation.
+    ///
+    /// Cards rotate clockwise around a pivot, which is the middle of the unrotated card's
+    /// bounding box (rounding up and to the left when a side has an even length). The pivot
+    /// stays in place when the card is rotated, like the cursor in the official game. For shapes
+    /// such as rings and some L shapes the middle of the bounding box is not inked, so the pivot
+    /// can be an empty square.
+    pub fn footprint(&self, rotation: Rotation) -> &Footprint {
+        &self.footprints[rotation as usize]
+    }
+}
+
+/// Works out the footprint of a card pattern in the given rotation. See [`Card::footprint`].
+fn footprint(tiles: &[[Square; CARD_SIZE]; CARD_SIZE], rotation: Rotation) -> Footprint {
+    let squares: Vec<_> = tiles
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &s)| s != Square::Empty)
+                .map(move |(x, &s)| (Coord::new(x as i32, y as i32), s))
+        })
+        .collect();
+
+    let Some((min, max)) = bounds(squares.iter().map(|&(c, _)| c)) else {
+        return Footprint::default();
+    };
+
+    let pivot = Coord::new(min.x + (max.x - min.x) / 2, min.y + (max.y - min.y) / 2);
+
+    let rotated: Vec<_> = squares
+        .into_iter()
+        .map(|(c, s)| {
+            (
+                Coord::new(c.x - pivot.x, c.y - pivot.y).rotated(rotation),
+                s,
+            )
+        })
+        .collect();
+
+    // Unwrapping is fine since the card has at least one square
+    let (min, max) = bounds(rotated.iter().map(|&(c, _)| c)).unwrap();
+
+    let mut squares: Vec<_> = rotated
+        .into_iter()
+        .map(|(c, s)| (Coord::new(c.x - min.x, c.y - min.y), s))
+        .collect();
+    squares.sort_by_key(|&(c, _)| (c.y, c.x));
+
+    Footprint {
+        width: max.x - min.x + 1,
+        height: max.y - min.y + 1,
+        pivot: Coord::new(-min.x, -min.y),
+        squares,
+    }
+}
+
+/// Returns the top left and bottom right corners of the smallest rectangle containing all the
+/// coordinates.
+fn bounds(coords: impl Iterator<Item = Coord>) -> Option<(Coord, Coord)> {
+    coords.fold(None, |acc, c| match acc {
+        None => Some((c, c)),
+        Some((min, max)) => Some((
+            Coord::new(min.x.min(c.x), min.y.min(c.y)),
+            Coord::new(max.x.max(c.x), max.y.max(c.y)),
+        )),
+    })
+}
+
+/// The squares of a card in a particular rotation, trimmed to their bounding box.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Footprint {
+    pub width: i32,
+    pub height: i32,
+    /// The position of the pivot within the bounding box. When a card is placed, the pivot goes
+    /// on the anchor coordinate, whether or not the card inks that square.
+    pub pivot: Coord,
+    /// The inked squares, relative to the top left of the bounding box and ordered by row.
+    pub squares: Vec<(Coord, Square)>,
+}
+
+impl Footprint {
+    /// Returns the square at the given position within the bounding box.
+    pub fn get(&self, coord: Coord) -> Square {
+        self.squares
+            .iter()
+            .find(|&&(c, _)| c == coord)
+            .map_or(Square::Empty, |&(_, s)| s)
+    }
+
+    /// Returns the positions of the special squares within the bounding box.
+    pub fn specials(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.squares
+            .iter()
+            .filter(|&&(_, s)| s == Square::Special)
+            .map(|&(c, _)| c)
+    }
+
+    /// Returns every square relative to the pivot, along with whether it is a special square.
+    pub fn offsets(&self) -> impl Iterator<Item = (Coord, bool)> + '_ {
+        self.squares.iter().map(|&(c, s)| {
+            (
+                Coord::new(c.x - self.pivot.x, c.y - self.pivot.y),
+                s == Square::Special,
+            )
+        })
+    }
 }
 
+/// The number of cards in a deck.
+pub const DECK_SIZE: usize = 15;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Deck {
-    cards: [CardID; 15],
+    cards: [CardID; DECK_SIZE],
+}
+
+/// The reason a deck is not allowed to be played.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "card", rename_all = "snake_case")]
+pub enum DeckError {
+    /// The card appears in the deck more than once.
+    Duplicate(CardID),
+    /// There is no card with this ID.
+    Unknown(CardID),
+    /// The card exists but is not part of the card set that players can use.
+    NotPlayable(CardID),
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckError::Duplicate(id) => write!(f, "card {id} is in the deck more than once"),
+            DeckError::Unknown(id) => write!(f, "card {id} does not exist"),
+            DeckError::NotPlayable(id) => write!(f, "card {id} is not allowed in decks"),
+        }
+    }
+}
+
+impl std::error::Error for DeckError {}
+
+impl Deck {
+    pub fn new(cards: [CardID; DECK_SIZE]) -> Self {
+        Self { cards }
+    }
+
+    pub fn cards(&self) -> &[CardID; DECK_SIZE] {
+        &self.cards
+    }
+
+    /// Checks that every card in the deck exists, is playable, and appears only once. Returns
+    /// the first problem found, in deck order.
+    pub fn validate(&self, catalog: &CardCatalog) -> Result<(), DeckError> {
+        let mut seen = BTreeSet::new();
+
+        for &id in &self.cards {
+            if catalog.get(id).is_none() {
+                return Err(DeckError::Unknown(id));
+            } else if !catalog.is_playable(id) {
+                return Err(DeckError::NotPlayable(id));
+            } else if !seen.insert(id) {
+                return Err(DeckError::Duplicate(id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a card pattern from rows of text, where `#` is ink, `*` is a special square and `.` is
+/// empty. Rows may be shorter than the card grid, in which case the rest is empty.
+fn parse_pattern<S: AsRef<str>>(rows: &[S]) -> Result<[[Square; CARD_SIZE]; CARD_SIZE], String> {
+    let mut tiles = [[Square::Empty; CARD_SIZE]; CARD_SIZE];
+
+    if rows.len() > CARD_SIZE {
+        return Err(format!("pattern has more than {CARD_SIZE} rows"));
+    }
+
+    for (y, row) in rows.iter().enumerate() {
+        let row = row.as_ref();
+
+        if row.chars().count() > CARD_SIZE {
+            return Err(format!("row {} is longer than {CARD_SIZE} squares", y + 1));
+        }
+
+        for (x, c) in row.chars().enumerate() {
+            tiles[y][x] = match c {
+                '.' => Square::Empty,
+                '#' => Square::Ink,
+                '*' => Square::Special,
+                c => return Err(format!("unexpected character {c:?} in row {}", y + 1)),
+            };
+        }
+    }
+
+    Ok(tiles)
+}
+
+/// A card as it is written in a card definition file.
+///
+/// Definition files are JSON arrays of objects like:
+///
+/// ```json
+/// {"id": 8, "name": "Splat Bomb", "cost": 1, "pattern": ["*#", "##"]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardDefinition {
+    pub id: CardID,
+    pub name: String,
+    /// The number of special points needed to play the card as a special attack.
+    pub cost: u32,
+    /// Rows of the card's pattern, where `#` is ink, `*` is a special square and `.` is empty.
+    pub pattern: Vec<String>,
+    /// Whether players may put the card in their deck. Defaults to true, and is used for cards
+    /// that exist but are not part of the current card set.
+    #[serde(default = "default_playable")]
+    pub playable: bool,
+}
+
+fn default_playable() -> bool {
+    true
+}
+
+impl TryFrom<CardDefinition> for Card {
+    type Error = CatalogError;
+
+    fn try_from(def: CardDefinition) -> Result<Self, Self::Error> {
+        let tiles =
+            parse_pattern(&def.pattern).map_err(|message| CatalogError::InvalidPattern {
+                id: def.id,
+                message,
+            })?;
+        let card = Card::new(def.id, def.name, def.cost, tiles);
+
+        if card.square_count() == 0 {
+            return Err(CatalogError::EmptyPattern(def.id));
+        }
+
+        Ok(card)
+    }
+}
+
+/// An error encountered while loading a card catalog.
+#[derive(Debug)]
+pub enum CatalogError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// A definition file was not valid. The path is None if the definitions were not loaded from
+    /// a file.
+    Json {
+        path: Option<PathBuf>,
+        error: serde_json::Error,
+    },
+    DuplicateId(CardID),
+    EmptyPattern(CardID),
+    InvalidPattern {
+        id: CardID,
+        message: String,
+    },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            CatalogError::Json {
+                path: Some(path),
+                error,
+            } => write!(f, "{}: {error}", path.display()),
+            CatalogError::Json { path: None, error } => write!(f, "{error}"),
+            CatalogError::DuplicateId(id) => write!(f, "more than one card has the id {id}"),
+            CatalogError::EmptyPattern(id) => write!(f, "card {id} has no squares"),
+            CatalogError::InvalidPattern { id, message } => {
+                write!(f, "card {id} has an invalid pattern: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogError::Io { error, .. } => Some(error),
+            CatalogError::Json { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A collection of every card that can be played, looked up by ID.
+#[derive(Debug, Clone, Default)]
+pub struct CardCatalog {
+    cards: BTreeMap<CardID, Card>,
+    /// Cards that exist but are not allowed in decks.
+    unplayable: BTreeSet<CardID>,
+}
+
+impl CardCatalog {
+    /// Returns the default card list that ships with the game: 24 cards with placeholder
+    /// patterns, rather than the official card set. Load the official list with
+    /// [`CardCatalog::load_dir`] to play with the real cards.
+    pub fn standard() -> Self {
+        Self::from_json(DEFAULT_CARDS).expect("the default card list should be valid")
+    }
+
+    /// Creates a catalog from a list of definitions, checking that every card has a valid
+    /// pattern and a unique ID.
+    pub fn from_definitions(
+        definitions: impl IntoIterator<Item = CardDefinition>,
+    ) -> Result<Self, CatalogError> {
+        let mut cards = BTreeMap::new();
+        let mut unplayable = BTreeSet::new();
+
+        for def in definitions {
+            let playable = def.playable;
+            let card = Card::try_from(def)?;
+
+            if cards.contains_key(&card.id()) {
+                return Err(CatalogError::DuplicateId(card.id()));
+            }
+
+            if !playable {
+                unplayable.insert(card.id());
+            }
+
+            cards.insert(card.id(), card);
+        }
+
+        Ok(Self { cards, unplayable })
+    }
+
+    /// Creates a catalog from a JSON array of card definitions.
+    pub fn from_json(json: &str) -> Result<Self, CatalogError> {
+        let definitions: Vec<CardDefinition> =
+            serde_json::from_str(json).map_err(|error| CatalogError::Json { path: None, error })?;
+
+        Self::from_definitions(definitions)
+    }
+
+    /// Loads every `.json` card definition file in the given directory into one catalog.
+    pub fn load_dir(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let path = path.as_ref();
+        let io_error = |path: &Path| {
+            let path = path.to_owned();
+            move |error| CatalogError::Io { path, error }
+        };
+
+        let mut files = Vec::new();
+
+        for entry in std::fs::read_dir(path).map_err(io_error(path))? {
+            let file = entry.map_err(io_error(path))?.path();
+
+            if file.extension().is_some_and(|ext| ext == "json") {
+                files.push(file);
+            }
+        }
+
+        // Sort so that errors are reported consistently
+        files.sort();
+
+        let mut definitions = Vec::new();
+
+        for file in files {
+            let json = std::fs::read_to_string(&file).map_err(io_error(&file))?;
+            let defs: Vec<CardDefinition> =
+                serde_json::from_str(&json).map_err(|error| CatalogError::Json {
+                    path: Some(file),
+                    error,
+                })?;
+
+            definitions.extend(defs);
+        }
+
+        Self::from_definitions(definitions)
+    }
+
+    pub fn get(&self, id: CardID) -> Option<&Card> {
+        self.cards.get(&id)
+    }
+
+    /// Returns whether the card exists and may be put in a deck.
+    pub fn is_playable(&self, id: CardID) -> bool {
+        self.cards.contains_key(&id) && !self.unplayable.contains(&id)
+    }
+
+    /// Returns every card in the catalog, ordered by ID.
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+/// Creates a card from rows of text in the same format as [`CardDefinition::pattern`].
+#[cfg(test)]
+pub(crate) fn test_card(rows: &[&str]) -> Card {
+    Card::new(0, "Test card", 3, parse_pattern(rows).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_square_count() {
+        assert_eq!(test_card(&["#*#", ".#"]).square_count(), 4);
+        assert_eq!(test_card(&[]).square_count(), 0);
+    }
+
+    #[test]
+    fn test_footprint_trimmed() {
+        let card = test_card(&["", "..##", "..*"]);
+        let footprint = card.footprint(Rotation::Up);
+
+        assert_eq!(footprint.width, 2);
+        assert_eq!(footprint.height, 2);
+        assert_eq!(footprint.pivot, Coord::new(0, 0));
+        assert_eq!(
+            footprint.squares,
+            vec![
+                (Coord::new(0, 0), Square::Ink),
+                (Coord::new(1, 0), Square::Ink),
+                (Coord::new(0, 1), Square::Special),
+            ]
+        );
+        assert_eq!(footprint.get(Coord::new(0, 1)), Square::Special);
+        assert_eq!(footprint.get(Coord::new(1, 1)), Square::Empty);
+    }
+
+    #[test]
+    fn test_footprint_rotation() {
+        // An L shape with the special square at the end of the long side
+        let card = test_card(&["#", "#", "##*"]);
+
+        let right = card.footprint(Rotation::Right);
+        assert_eq!((right.width, right.height), (3, 3));
+        assert_eq!(right.pivot, Coord::new(1, 1));
+        assert_eq!(right.specials().collect::<Vec<_>>(), vec![Coord::new(0, 2)]);
+
+        let down = card.footprint(Rotation::Down);
+        assert_eq!(down.specials().collect::<Vec<_>>(), vec![Coord::new(0, 0)]);
+        assert_eq!(down.get(Coord::new(2, 2)), Square::Ink);
+
+        let left = card.footprint(Rotation::Left);
+        assert_eq!(left.specials().collect::<Vec<_>>(), vec![Coord::new(2, 0)]);
+    }
+
+    #[test]
+    fn test_footprint_pivot() {
+        // Even lengths round up and to the left
+        let card = test_card(&["####"]);
+        let up = card.footprint(Rotation::Up);
+        assert_eq!(up.pivot, Coord::new(1, 0));
+
+        // The pivot stays in place, so rotating right turns the line into a column running
+        // from one above the pivot to two below it
+        let right = card.footprint(Rotation::Right);
+        assert_eq!((right.width, right.height), (1, 4));
+        assert_eq!(right.pivot, Coord::new(0, 1));
+
+        for rotation in Rotation::ALL {
+            let footprint = card.footprint(rotation);
+            assert!(footprint.offsets().any(|(c, _)| c == Coord::new(0, 0)));
+        }
+    }
+
+    #[test]
+    fn test_footprint_empty_pivot() {
+        // A ring has nothing in the middle of its bounding box, but still rotates around it
+        let ring = test_card(&["##*", "#.#", "###"]);
+        for rotation in Rotation::ALL {
+            let footprint = ring.footprint(rotation);
+            assert_eq!(footprint.pivot, Coord::new(1, 1));
+            assert_eq!(footprint.get(footprint.pivot), Square::Empty);
+            assert!(footprint.offsets().all(|(c, _)| c != Coord::new(0, 0)));
+        }
+
+        // The corner of this L is outside the middle too, so rotating keeps the empty square in
+        // place and swings the arms around it
+        let card = test_card(&["#..", "#..", "##*"]);
+        let up = card.footprint(Rotation::Up);
+        assert_eq!(up.pivot, Coord::new(1, 1));
+        assert_eq!(up.get(up.pivot), Square::Empty);
+
+        let right = card.footprint(Rotation::Right);
+        assert_eq!(right.pivot, Coord::new(1, 1));
+        assert_eq!(right.get(right.pivot), Square::Empty);
+        assert_eq!(right.specials().collect::<Vec<_>>(), vec![Coord::new(0, 2)]);
+        assert_eq!(right.get(Coord::new(2, 0)), Square::Ink);
+    }
+
+    #[test]
+    fn test_card_serde_keeps_footprints() {
+        let card = test_card(&["#.", "#*"]);
+        let json = serde_json::to_string(&card).unwrap();
+        assert!(!json.contains("footprints"));
+
+        let parsed: Card = serde_json::from_str(&json).unwrap();
+        for rotation in Rotation::ALL {
+            assert_eq!(parsed.footprint(rotation), card.footprint(rotation));
+        }
+    }
+
+    fn definition(id: CardID, pattern: &[&str]) -> CardDefinition {
+        CardDefinition {
+            id,
+            name: format!("Card {id}"),
+            cost: 2,
+            pattern: pattern.iter().map(|s| s.to_string()).collect(),
+            playable: true,
+        }
+    }
+
+    #[test]
+    fn test_standard_catalog() {
+        let catalog = CardCatalog::standard();
+
+        assert!(catalog.len() >= 15);
+        for card in catalog.cards() {
+            assert_eq!(
+                card.footprint(Rotation::Up).specials().count(),
+                1,
+                "{} should have one special square",
+                card.name()
+            );
+        }
+
+        let card = catalog.get(8).unwrap();
+        assert_eq!(card.name(), "Splat Bomb");
+        assert_eq!(card.cost(), 1);
+        assert_eq!(card.square_count(), 4);
+    }
+
+    #[test]
+    fn test_catalog_validation() {
+        let catalog =
+            CardCatalog::from_definitions([definition(1, &["#*"]), definition(2, &["*"])]);
+        assert_eq!(catalog.unwrap().len(), 2);
+
+        assert!(matches!(
+            CardCatalog::from_definitions([definition(1, &["#*"]), definition(1, &["*"])]),
+            Err(CatalogError::DuplicateId(1))
+        ));
+        assert!(matches!(
+            CardCatalog::from_definitions([definition(3, &["...", ""])]),
+            Err(CatalogError::EmptyPattern(3))
+        ));
+        assert!(matches!(
+            CardCatalog::from_definitions([definition(4, &["#x"])]),
+            Err(CatalogError::InvalidPattern { id: 4, .. })
+        ));
+        assert!(matches!(
+            CardCatalog::from_definitions([definition(5, &["#########"])]),
+            Err(CatalogError::InvalidPattern { id: 5, .. })
+        ));
+        assert!(matches!(
+            CardCatalog::from_json("{}"),
+            Err(CatalogError::Json { path: None, .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = std::env::temp_dir().join(format!("tableturf-cards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.json"),
+            r#"[{"id": 1, "name": "A", "cost": 1, "pattern": ["*"]}]"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("b.json"),
+            r##"[{"id": 2, "name": "B", "cost": 1, "pattern": ["#*"]}]"##,
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a card").unwrap();
+
+        let catalog = CardCatalog::load_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let catalog = catalog.unwrap();
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog.get(2).unwrap().name(), "B");
+        assert!(catalog.get(3).is_none());
+    }
+
+    #[test]
+    fn test_deck_validation() {
+        let mut definitions: Vec<_> = (1..=20).map(|id| definition(id, &["*"])).collect();
+        definitions[19].playable = false;
+        let catalog = CardCatalog::from_definitions(definitions).unwrap();
+
+        let valid = Deck::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(valid.validate(&catalog), Ok(()));
+
+        let duplicate = Deck::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 1]);
+        assert_eq!(duplicate.validate(&catalog), Err(DeckError::Duplicate(1)));
+
+        let cheating = Deck::new([7; DECK_SIZE]);
+        assert_eq!(cheating.validate(&catalog), Err(DeckError::Duplicate(7)));
+
+        let unknown = Deck::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 99]);
+        assert_eq!(unknown.validate(&catalog), Err(DeckError::Unknown(99)));
+
+        let unplayable = Deck::new([20, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(
+            unplayable.validate(&catalog),
+            Err(DeckError::NotPlayable(20))
+        );
+    }
+
+    #[test]
+    fn test_playable_defaults_to_true() {
+        let catalog = CardCatalog::from_json(
+            r#"[{"id": 1, "name": "A", "cost": 1, "pattern": ["*"]},
+                {"id": 2, "name": "B", "cost": 1, "pattern": ["*"], "playable": false}]"#,
+        )
+        .unwrap();
+
+        assert!(catalog.is_playable(1));
+        assert!(!catalog.is_playable(2));
+        assert!(!catalog.is_playable(3));
+    }
 }
@@ -0,0 +1,932 @@
+//! The state of a match in progress.
+//!
+//! [`GameState`] is the single source of truth for a match. It is driven entirely by
+//! [`GameState::apply`], and has no networking of its own. Its only source of randomness is an
+//! [`Rng`] created from the seed it was started with, so the same seed and the same actions always
+//! produce the same match.
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, Coord, PlacementError, Rotation},
+    cards::{Card, CardID},
+    protocol::PlayerId,
+    rng::Rng,
+    turn::{resolve_turn, InvalidMove, Move, Placement, TileChange},
+};
+
+/// The number of cards in a player's hand.
+pub const HAND_SIZE: usize = 4;
+/// The number of turns in a match.
+pub const TURN_COUNT: u32 = 12;
+
+/// A move submitted by a player for the current turn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayerMove {
+    /// Discard the given card from the hand without placing it, gaining a special point.
+    Pass { card: CardID },
+    /// Place the given card from the hand onto the board.
+    Place {
+        card: CardID,
+        rotation: Rotation,
+        anchor: Coord,
+        special: bool,
+    },
+}
+
+impl PlayerMove {
+    /// The card from the player's hand that is used by this move.
+    pub fn card(&self) -> CardID {
+        match *self {
+            PlayerMove::Pass { card } | PlayerMove::Place { card, .. } => card,
+        }
+    }
+}
+
+/// Something a player does that changes the state of the match.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Decide whether to redraw the opening hand. Each player must decide exactly once before
+    /// the first turn.
+    Redraw { player: PlayerId, redraw: bool },
+    /// Lock in a move for the current turn.
+    Play { player: PlayerId, mv: PlayerMove },
+    /// Give up the match, which the opponent wins regardless of the score.
+    Forfeit { player: PlayerId },
+}
+
+impl Action {
+    pub fn player(&self) -> PlayerId {
+        match *self {
+            Action::Redraw { player, .. }
+            | Action::Play { player, .. }
+            | Action::Forfeit { player } => player,
+        }
+    }
+}
+
+/// What happened as a result of playing a turn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnOutcome {
+    /// Every tile that changed, ordered by coordinate.
+    pub diff: Vec<TileChange>,
+    /// Special tiles that became activated this turn.
+    pub activated: Vec<Coord>,
+    /// Both players' special points after the turn.
+    pub special_points: [u32; 2],
+}
+
+/// Something that happened as a result of an [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The player was given a new hand after redrawing.
+    HandDealt { player: PlayerId, hand: Vec<CardID> },
+    /// A new turn has started and players may submit moves.
+    TurnStarted { turn: u32 },
+    /// The player has locked in their move for this turn.
+    MoveLocked { player: PlayerId },
+    /// Both players have moved and the turn has been resolved.
+    TurnResolved {
+        turn: u32,
+        moves: [PlayerMove; 2],
+        outcome: TurnOutcome,
+    },
+    /// The player drew a card from their draw pile.
+    CardDrawn { player: PlayerId, card: CardID },
+    /// The player gave up the match. This is always followed by [`Event::GameOver`].
+    Forfeited { player: PlayerId },
+    /// The last turn has been played. `winner` is None if the match was a draw.
+    GameOver {
+        scores: [u32; 2],
+        winner: Option<PlayerId>,
+    },
+}
+
+/// The reason an [`Action`] could not be applied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "details", rename_all = "snake_case")]
+pub enum ActionError {
+    /// The players are still deciding whether to redraw, so moves can't be made yet.
+    NotStarted,
+    /// The redraw decision has already been made.
+    AlreadyRedrawn,
+    /// The player has already locked in a move for this turn.
+    AlreadyMoved,
+    /// The match is over.
+    GameOver,
+    /// The player does not have the given card in their hand.
+    CardNotInHand(CardID),
+    /// The card can't be placed there.
+    InvalidPlacement(PlacementError),
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::NotStarted => write!(f, "the first turn has not started yet"),
+            ActionError::AlreadyRedrawn => write!(f, "redraw has already been decided"),
+            ActionError::AlreadyMoved => write!(f, "a move has already been made this turn"),
+            ActionError::GameOver => write!(f, "the match is over"),
+            ActionError::CardNotInHand(id) => write!(f, "card {id} is not in the player's hand"),
+            ActionError::InvalidPlacement(e) => write!(f, "invalid placement: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Phase {
+    /// Players are deciding whether to redraw their opening hand.
+    Redraw {
+        decided: [bool; 2],
+    },
+    /// Players are choosing their moves for a turn.
+    Turn {
+        moves: [Option<PlayerMove>; 2],
+    },
+    Finished,
+}
+
+/// Everything needed to recreate a match at the start of a turn, see [`GameState::from_parts`].
+#[derive(Debug, Clone)]
+pub(crate) struct GameParts {
+    pub board: Board,
+    /// The current turn, starting at 1.
+    pub turn: u32,
+    pub hands: [Vec<Card>; 2],
+    /// The cards each player will draw, in order.
+    pub draw_piles: [Vec<Card>; 2],
+    pub decks: [Vec<Card>; 2],
+    pub played: [Vec<CardID>; 2],
+    pub special_points: [u32; 2],
+    /// Special tiles that have already granted their owner a point.
+    pub activated: BTreeSet<Coord>,
+}
+
+/// The authoritative state of a match.
+#[derive(Debug, Clone)]
+pub struct GameState {
+    board: Board,
+    phase: Phase,
+    /// The current turn, starting at 1.
+    turn: u32,
+    hands: [Vec<Card>; 2],
+    draw_piles: [VecDeque<Card>; 2],
+    /// Both players' decks, in the order they were chosen.
+    decks: [Vec<Card>; 2],
+    /// The cards each player has played or passed with so far, in order.
+    played: [Vec<CardID>; 2],
+    special_points: [u32; 2],
+    /// Special tiles that have been surrounded and already granted their owner a point.
+    activated: BTreeSet<Coord>,
+    /// The seed the match was started with.
+    seed: u64,
+    rng: Rng,
+}
+
+impl GameState {
+    /// Starts a new match on the given board, shuffling both decks and dealing each player an
+    /// opening hand.
+    pub fn new(board: Board, decks: [Vec<Card>; 2], seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let [mut deck1, mut deck2] = decks.clone();
+        rng.shuffle(&mut deck1);
+        rng.shuffle(&mut deck2);
+
+        let mut draw_piles = [VecDeque::from(deck1), VecDeque::from(deck2)];
+        let hands = [
+            draw(&mut draw_piles[0], HAND_SIZE),
+            draw(&mut draw_piles[1], HAND_SIZE),
+        ];
+
+        Self {
+            board,
+            phase: Phase::Redraw {
+                decided: [false; 2],
+            },
+            turn: 1,
+            hands,
+            draw_piles,
+            decks,
+            played: [Vec::new(), Vec::new()],
+            special_points: [0; 2],
+            activated: BTreeSet::new(),
+            seed,
+            rng,
+        }
+    }
+
+    /// Recreates a match in progress at the start of a turn, for example so that bots can
+    /// simulate how it might play out.
+    pub(crate) fn from_parts(parts: GameParts) -> Self {
+        Self {
+            board: parts.board,
+            phase: Phase::Turn { moves: [None; 2] },
+            turn: parts.turn,
+            hands: parts.hands,
+            draw_piles: parts.draw_piles.map(VecDeque::from),
+            decks: parts.decks,
+            played: parts.played,
+            special_points: parts.special_points,
+            activated: parts.activated,
+            seed: 0,
+            rng: Rng::new(0),
+        }
+    }
+
+    /// Returns the seed the match was started with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the current turn, starting at 1.
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn hand(&self, player: PlayerId) -> &[Card] {
+        &self.hands[player as usize]
+    }
+
+    /// Returns the player's deck as it was chosen. Decks are not secret, only the order they are
+    /// drawn in.
+    pub fn deck(&self, player: PlayerId) -> &[Card] {
+        &self.decks[player as usize]
+    }
+
+    /// Returns the cards the player has used so far (whether they were placed or passed with), in
+    /// the order they were used.
+    pub fn played(&self, player: PlayerId) -> &[CardID] {
+        &self.played[player as usize]
+    }
+
+    /// Returns the number of cards the player has left to draw.
+    pub fn draw_pile_len(&self, player: PlayerId) -> usize {
+        self.draw_piles[player as usize].len()
+    }
+
+    /// Returns the number of special points the player has available to spend.
+    pub fn special_points(&self, player: PlayerId) -> u32 {
+        self.special_points[player as usize]
+    }
+
+    /// Returns whether the special tile at the given coordinate has been activated.
+    pub fn is_activated(&self, coord: Coord) -> bool {
+        self.activated.contains(&coord)
+    }
+
+    /// Returns whether the player has locked in a move for the current turn.
+    pub fn has_moved(&self, player: PlayerId) -> bool {
+        matches!(&self.phase, Phase::Turn { moves } if moves[player as usize].is_some())
+    }
+
+    /// Returns whether both players have decided whether to redraw, so turns are being played.
+    pub fn has_started(&self) -> bool {
+        !matches!(self.phase, Phase::Redraw { .. })
+    }
+
+    /// Returns whether the match can't continue until the player acts, either by deciding whether
+    /// to redraw or by locking in a move.
+    pub fn is_waiting_for(&self, player: PlayerId) -> bool {
+        match &self.phase {
+            Phase::Redraw { decided } => !decided[player as usize],
+            Phase::Turn { moves } => moves[player as usize].is_none(),
+            Phase::Finished => false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase == Phase::Finished
+    }
+
+    /// Returns each player's current score, which is the number of tiles they have inked.
+    pub fn scores(&self) -> [u32; 2] {
+        [
+            self.board.ink_count(PlayerId::P1),
+            self.board.ink_count(PlayerId::P2),
+        ]
+    }
+
+    /// Applies a player's action, returning everything that happened as a result.
+    ///
+    /// If the action is not allowed, the state is left unchanged.
+    pub fn apply(&mut self, action: Action) -> Result<Vec<Event>, ActionError> {
+        match action {
+            Action::Redraw { player, redraw } => self.redraw(player, redraw),
+            Action::Play { player, mv } => self.play(player, mv),
+            Action::Forfeit { player } => self.forfeit(player),
+        }
+    }
+
+    fn forfeit(&mut self, player: PlayerId) -> Result<Vec<Event>, ActionError> {
+        if self.phase == Phase::Finished {
+            return Err(ActionError::GameOver);
+        }
+
+        self.phase = Phase::Finished;
+
+        Ok(vec![
+            Event::Forfeited { player },
+            Event::GameOver {
+                scores: self.scores(),
+                winner: Some(player.opponent()),
+            },
+        ])
+    }
+
+    fn redraw(&mut self, player: PlayerId, redraw: bool) -> Result<Vec<Event>, ActionError> {
+        let Phase::Redraw { decided } = &mut self.phase else {
+            return Err(match self.phase {
+                Phase::Finished => ActionError::GameOver,
+                _ => ActionError::AlreadyRedrawn,
+            });
+        };
+
+        if decided[player as usize] {
+            return Err(ActionError::AlreadyRedrawn);
+        }
+
+        decided[player as usize] = true;
+        let started = decided.iter().all(|&d| d);
+        let mut events = Vec::new();
+
+        if redraw {
+            let i = player as usize;
+            self.draw_piles[i].extend(self.hands[i].drain(..));
+            self.rng.shuffle(self.draw_piles[i].make_contiguous());
+            self.hands[i] = draw(&mut self.draw_piles[i], HAND_SIZE);
+
+            events.push(Event::HandDealt {
+                player,
+                hand: self.hands[i].iter().map(Card::id).collect(),
+            });
+        }
+
+        if started {
+            self.phase = Phase::Turn { moves: [None; 2] };
+            events.push(Event::TurnStarted { turn: self.turn });
+        }
+
+        Ok(events)
+    }
+
+    fn play(&mut self, player: PlayerId, mv: PlayerMove) -> Result<Vec<Event>, ActionError> {
+        let moves = match &self.phase {
+            Phase::Redraw { .. } => return Err(ActionError::NotStarted),
+            Phase::Finished => return Err(ActionError::GameOver),
+            Phase::Turn { moves } => moves,
+        };
+
+        if moves[player as usize].is_some() {
+            return Err(ActionError::AlreadyMoved);
+        }
+
+        let card = self
+            .find_card(player, mv.card())
+            .ok_or(ActionError::CardNotInHand(mv.card()))?;
+
+        if let PlayerMove::Place {
+            rotation,
+            anchor,
+            special,
+            ..
+        } = mv
+        {
+            if special {
+                let points = self.special_points(player);
+
+                if card.cost() > points {
+                    return Err(ActionError::InvalidPlacement(
+                        PlacementError::NotEnoughSpecial {
+                            cost: card.cost(),
+                            points,
+                        },
+                    ));
+                }
+
+                self.board
+                    .check_special_placement(card, rotation, anchor, player)
+                    .map_err(ActionError::InvalidPlacement)?;
+            } else {
+                self.board
+                    .check_placement(card, rotation, anchor, player)
+                    .map_err(ActionError::InvalidPlacement)?;
+            }
+        }
+
+        let Phase::Turn { moves } = &mut self.phase else {
+            unreachable!()
+        };
+        moves[player as usize] = Some(mv);
+
+        let mut events = vec![Event::MoveLocked { player }];
+
+        if let [Some(mv1), Some(mv2)] = *moves {
+            events.extend(self.end_turn([mv1, mv2]));
+        }
+
+        Ok(events)
+    }
+
+    /// Resolves the turn once both players have moved, then either starts the next turn or ends
+    /// the match.
+    fn end_turn(&mut self, moves: [PlayerMove; 2]) -> Vec<Event> {
+        let cards = [
+            self.find_card(PlayerId::P1, moves[0].card()).cloned(),
+            self.find_card(PlayerId::P2, moves[1].card()).cloned(),
+        ];
+        let [Some(card1), Some(card2)] = cards else {
+            unreachable!("moves are checked to be in the hand when they are submitted");
+        };
+
+        let outcome = self
+            .play_turn(&to_move(&moves[0], &card1), &to_move(&moves[1], &card2))
+            .expect("moves are checked to be legal when they are submitted");
+
+        for (player, mv) in [(PlayerId::P1, moves[0]), (PlayerId::P2, moves[1])] {
+            let hand = &mut self.hands[player as usize];
+            if let Some(i) = hand.iter().position(|c| c.id() == mv.card()) {
+                hand.remove(i);
+            }
+            self.played[player as usize].push(mv.card());
+        }
+
+        let mut events = vec![Event::TurnResolved {
+            turn: self.turn,
+            moves,
+            outcome,
+        }];
+
+        if self.turn == TURN_COUNT {
+            let scores = self.scores();
+            let winner = match scores[0].cmp(&scores[1]) {
+                std::cmp::Ordering::Greater => Some(PlayerId::P1),
+                std::cmp::Ordering::Less => Some(PlayerId::P2),
+                std::cmp::Ordering::Equal => None,
+            };
+
+            self.phase = Phase::Finished;
+            events.push(Event::GameOver { scores, winner });
+        } else {
+            for player in [PlayerId::P1, PlayerId::P2] {
+                let i = player as usize;
+                if let Some(card) = self.draw_piles[i].pop_front() {
+                    events.push(Event::CardDrawn {
+                        player,
+                        card: card.id(),
+                    });
+                    self.hands[i].push(card);
+                }
+            }
+
+            self.turn += 1;
+            self.phase = Phase::Turn { moves: [None; 2] };
+            events.push(Event::TurnStarted { turn: self.turn });
+        }
+
+        events
+    }
+
+    fn find_card(&self, player: PlayerId, id: CardID) -> Option<&Card> {
+        self.hands[player as usize].iter().find(|c| c.id() == id)
+    }
+
+    /// Plays both players' moves and updates the special gauges.
+    ///
+    /// Special attacks cost the card's special cost, passing grants a point, and every special
+    /// tile that is surrounded on all 8 sides by the end of the turn grants its owner a point the
+    /// first time that happens.
+    fn play_turn(&mut self, p1: &Move, p2: &Move) -> Result<TurnOutcome, InvalidMove> {
+        let moves = [(PlayerId::P1, p1), (PlayerId::P2, p2)];
+
+        for (player, mv) in moves {
+            if let Move::Place(p) = mv {
+                let points = self.special_points(player);
+
+                if p.special && p.card.cost() > points {
+                    return Err(InvalidMove {
+                        player,
+                        error: PlacementError::NotEnoughSpecial {
+                            cost: p.card.cost(),
+                            points,
+                        },
+                    });
+                }
+            }
+        }
+
+        let result = resolve_turn(&self.board, p1, p2)?;
+        self.board = result.board;
+
+        for (player, mv) in moves {
+            match mv {
+                Move::Pass => self.special_points[player as usize] += 1,
+                Move::Place(p) if p.special => {
+                    self.special_points[player as usize] -= p.card.cost()
+                }
+                Move::Place(_) => {}
+            }
+        }
+
+        let mut activated: Vec<_> = self
+            .board
+            .special_tiles()
+            .filter(|&(coord, _)| {
+                !self.activated.contains(&coord) && self.board.is_surrounded(coord)
+            })
+            .collect();
+        activated.sort_by_key(|&(coord, _)| coord);
+
+        for &(coord, owner) in &activated {
+            self.activated.insert(coord);
+            self.special_points[owner as usize] += 1;
+        }
+
+        Ok(TurnOutcome {
+            diff: result.diff,
+            activated: activated.into_iter().map(|(coord, _)| coord).collect(),
+            special_points: self.special_points,
+        })
+    }
+}
+
+/// Takes up to `count` cards from the top of the pile.
+fn draw(pile: &mut VecDeque<Card>, count: usize) -> Vec<Card> {
+    let count = count.min(pile.len());
+    pile.drain(..count).collect()
+}
+
+pub(crate) fn to_move<'a>(mv: &PlayerMove, card: &'a Card) -> Move<'a> {
+    match *mv {
+        PlayerMove::Pass { .. } => Move::Pass,
+        PlayerMove::Place {
+            rotation,
+            anchor,
+            special,
+            ..
+        } => Move::Place(Placement {
+            card,
+            rotation,
+            anchor,
+            special,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        board::Tile,
+        cards::{test_card, Square, CARD_SIZE},
+    };
+
+    fn place(card: &Card, x: i32, y: i32, special: bool) -> Move<'_> {
+        Move::Place(Placement {
+            card,
+            rotation: Rotation::Up,
+            anchor: Coord::new(x, y),
+            special,
+        })
+    }
+
+    /// A deck of 15 single square cards with IDs starting at `first_id`.
+    fn deck(first_id: CardID) -> Vec<Card> {
+        let mut tiles = [[Square::Empty; CARD_SIZE]; CARD_SIZE];
+        tiles[0][0] = Square::Ink;
+
+        (first_id..first_id + 15)
+            .map(|id| Card::new(id, format!("Card {id}"), 3, tiles))
+            .collect()
+    }
+
+    /// A 3x6 board with a P1 special tile in the top left corner and a P2 special tile in the
+    /// bottom right corner.
+    fn game() -> GameState {
+        let mut board = Board::new("Test board", 3, 6);
+        board.set(
+            Coord::new(0, 0),
+            Tile::Ink {
+                owner: PlayerId::P1,
+                special: true,
+            },
+        );
+        board.set(
+            Coord::new(2, 5),
+            Tile::Ink {
+                owner: PlayerId::P2,
+                special: true,
+            },
+        );
+        GameState::new(board, [deck(0), deck(100)], 0)
+    }
+
+    /// A game where both players have chosen not to redraw.
+    fn started_game() -> GameState {
+        let mut game = game();
+        for player in [PlayerId::P1, PlayerId::P2] {
+            game.apply(Action::Redraw {
+                player,
+                redraw: false,
+            })
+            .unwrap();
+        }
+        game
+    }
+
+    fn pass(player: PlayerId, card: CardID) -> Action {
+        Action::Play {
+            player,
+            mv: PlayerMove::Pass { card },
+        }
+    }
+
+    fn hand_ids(game: &GameState, player: PlayerId) -> Vec<CardID> {
+        game.hand(player).iter().map(Card::id).collect()
+    }
+
+    #[test]
+    fn test_pass_grants_point() {
+        let mut game = game();
+        let outcome = game.play_turn(&Move::Pass, &Move::Pass).unwrap();
+
+        assert_eq!(outcome.special_points, [1, 1]);
+        assert_eq!(game.special_points(PlayerId::P1), 1);
+    }
+
+    #[test]
+    fn test_surrounded_special_activates_once() {
+        let mut game = game();
+        let c = test_card(&[".#", "##"]);
+        let outcome = game
+            .play_turn(&place(&c, 0, 0, false), &Move::Pass)
+            .unwrap();
+
+        assert_eq!(outcome.activated, vec![Coord::new(0, 0)]);
+        assert_eq!(outcome.special_points, [1, 1]);
+        assert!(game.is_activated(Coord::new(0, 0)));
+
+        let outcome = game.play_turn(&Move::Pass, &Move::Pass).unwrap();
+        assert!(outcome.activated.is_empty());
+        assert_eq!(outcome.special_points, [2, 2]);
+    }
+
+    #[test]
+    fn test_special_attack_costs_points() {
+        let mut game = game();
+        let c = test_card(&["#"]);
+
+        assert_eq!(
+            game.play_turn(&place(&c, 1, 0, true), &Move::Pass),
+            Err(InvalidMove {
+                player: PlayerId::P1,
+                error: PlacementError::NotEnoughSpecial { cost: 3, points: 0 }
+            })
+        );
+
+        for _ in 0..3 {
+            game.play_turn(&Move::Pass, &Move::Pass).unwrap();
+        }
+
+        let outcome = game.play_turn(&place(&c, 1, 0, true), &Move::Pass).unwrap();
+        assert_eq!(outcome.special_points, [0, 4]);
+    }
+
+    #[test]
+    fn test_redraw() {
+        let mut game = game();
+        let opening = hand_ids(&game, PlayerId::P2);
+
+        assert_eq!(game.hand(PlayerId::P1).len(), HAND_SIZE);
+        assert!(!game.has_started());
+        assert_eq!(
+            game.apply(pass(PlayerId::P1, 0)),
+            Err(ActionError::NotStarted)
+        );
+
+        let events = game
+            .apply(Action::Redraw {
+                player: PlayerId::P1,
+                redraw: true,
+            })
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![Event::HandDealt {
+                player: PlayerId::P1,
+                hand: hand_ids(&game, PlayerId::P1)
+            }]
+        );
+        assert_eq!(game.hand(PlayerId::P1).len(), HAND_SIZE);
+        assert_eq!(game.draw_pile_len(PlayerId::P1), 11);
+
+        assert_eq!(
+            game.apply(Action::Redraw {
+                player: PlayerId::P1,
+                redraw: true
+            }),
+            Err(ActionError::AlreadyRedrawn)
+        );
+
+        let events = game
+            .apply(Action::Redraw {
+                player: PlayerId::P2,
+                redraw: false,
+            })
+            .unwrap();
+        assert_eq!(events, vec![Event::TurnStarted { turn: 1 }]);
+        assert_eq!(hand_ids(&game, PlayerId::P2), opening);
+    }
+
+    #[test]
+    fn test_seed_reproduces_match() {
+        let redraw_p1 = |game: &mut GameState| {
+            game.apply(Action::Redraw {
+                player: PlayerId::P1,
+                redraw: true,
+            })
+            .unwrap()
+        };
+
+        let mut a = game();
+        let mut b = game();
+        assert_eq!(a.seed(), 0);
+        assert_eq!(hand_ids(&a, PlayerId::P1), hand_ids(&b, PlayerId::P1));
+        assert_eq!(hand_ids(&a, PlayerId::P2), hand_ids(&b, PlayerId::P2));
+        assert_eq!(redraw_p1(&mut a), redraw_p1(&mut b));
+
+        // The decks are shuffled, and differently for each player
+        let p1: Vec<_> = hand_ids(&a, PlayerId::P1);
+        let p2: Vec<_> = hand_ids(&a, PlayerId::P2)
+            .iter()
+            .map(|id| id - 100)
+            .collect();
+        assert_ne!(p1, vec![0, 1, 2, 3]);
+        assert_ne!(p1, p2);
+
+        let other = GameState::new(a.board().clone(), [deck(0), deck(100)], 1);
+        assert_ne!(
+            hand_ids(&other, PlayerId::P1),
+            hand_ids(&game(), PlayerId::P1)
+        );
+    }
+
+    #[test]
+    fn test_turn_flow() {
+        let mut game = started_game();
+        let hand = hand_ids(&game, PlayerId::P1);
+
+        assert_eq!(
+            game.apply(pass(PlayerId::P1, 50)),
+            Err(ActionError::CardNotInHand(50))
+        );
+        assert_eq!(
+            game.apply(Action::Play {
+                player: PlayerId::P1,
+                mv: PlayerMove::Place {
+                    card: hand[0],
+                    rotation: Rotation::Up,
+                    anchor: Coord::new(2, 2),
+                    special: false
+                }
+            }),
+            Err(ActionError::InvalidPlacement(PlacementError::NotConnected))
+        );
+
+        let place = Action::Play {
+            player: PlayerId::P1,
+            mv: PlayerMove::Place {
+                card: hand[0],
+                rotation: Rotation::Up,
+                anchor: Coord::new(1, 0),
+                special: false,
+            },
+        };
+        assert_eq!(
+            game.apply(place).unwrap(),
+            vec![Event::MoveLocked {
+                player: PlayerId::P1
+            }]
+        );
+        assert!(game.has_moved(PlayerId::P1));
+        assert!(!game.is_waiting_for(PlayerId::P1));
+        assert!(game.is_waiting_for(PlayerId::P2));
+        assert_eq!(game.apply(place), Err(ActionError::AlreadyMoved));
+
+        let card2 = game.hand(PlayerId::P2)[0].id();
+        let events = game.apply(pass(PlayerId::P2, card2)).unwrap();
+        assert_eq!(
+            events[0],
+            Event::MoveLocked {
+                player: PlayerId::P2
+            }
+        );
+        assert!(matches!(events[1], Event::TurnResolved { turn: 1, .. }));
+        let drawn = [
+            game.hand(PlayerId::P1)[HAND_SIZE - 1].id(),
+            game.hand(PlayerId::P2)[HAND_SIZE - 1].id(),
+        ];
+        assert_eq!(
+            events[2..],
+            [
+                Event::CardDrawn {
+                    player: PlayerId::P1,
+                    card: drawn[0]
+                },
+                Event::CardDrawn {
+                    player: PlayerId::P2,
+                    card: drawn[1]
+                },
+                Event::TurnStarted { turn: 2 },
+            ]
+        );
+
+        assert_eq!(game.scores(), [2, 1]);
+        assert_eq!(game.special_points(PlayerId::P2), 1);
+        assert_eq!(hand_ids(&game, PlayerId::P1)[..3], hand[1..]);
+        assert_eq!(game.draw_pile_len(PlayerId::P1), 10);
+        assert_eq!(game.played(PlayerId::P1), [hand[0]]);
+        assert_eq!(game.played(PlayerId::P2), [card2]);
+
+        // Decks stay in the order they were chosen
+        let deck: Vec<_> = game.deck(PlayerId::P2).iter().map(Card::id).collect();
+        assert_eq!(deck, (100..115).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_full_match() {
+        let mut game = started_game();
+
+        for turn in 1..=TURN_COUNT {
+            assert_eq!(game.turn(), turn);
+
+            let card1 = game.hand(PlayerId::P1)[0].id();
+            let card2 = game.hand(PlayerId::P2)[0].id();
+            game.apply(pass(PlayerId::P1, card1)).unwrap();
+            let events = game.apply(pass(PlayerId::P2, card2)).unwrap();
+
+            if turn == TURN_COUNT {
+                assert_eq!(
+                    events.last(),
+                    Some(&Event::GameOver {
+                        scores: [1, 1],
+                        winner: None
+                    })
+                );
+            }
+        }
+
+        assert!(game.is_finished());
+        assert!(!game.is_waiting_for(PlayerId::P1));
+        assert_eq!(game.special_points(PlayerId::P1), TURN_COUNT);
+        assert_eq!(game.hand(PlayerId::P1).len(), 3);
+        assert_eq!(game.draw_pile_len(PlayerId::P1), 0);
+        assert_eq!(
+            game.apply(pass(PlayerId::P1, 0)),
+            Err(ActionError::GameOver)
+        );
+    }
+
+    #[test]
+    fn test_forfeit() {
+        let mut game = started_game();
+        assert!(game.has_started());
+
+        let events = game
+            .apply(Action::Forfeit {
+                player: PlayerId::P2,
+            })
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::Forfeited {
+                    player: PlayerId::P2
+                },
+                Event::GameOver {
+                    scores: [1, 1],
+                    winner: Some(PlayerId::P1)
+                },
+            ]
+        );
+        assert!(game.is_finished());
+        assert_eq!(
+            game.apply(Action::Forfeit {
+                player: PlayerId::P1
+            }),
+            Err(ActionError::GameOver)
+        );
+    }
+}
@@ -1,3 +1,12 @@
+pub mod ai;
+pub mod bitboard;
 pub mod board;
 pub mod cards;
+pub mod game;
+pub mod map;
+pub mod moves;
 pub mod protocol;
+pub mod replay;
+pub mod rng;
+pub mod stages;
+pub mod turn;
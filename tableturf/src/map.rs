@@ -0,0 +1,282 @@
+//! A plain text format for tableturf boards.
+//!
+//! A map file starts with a header line giving the board's name, followed by one line per row of
+//! the board. Every row must be the same length. For example:
+//!
+//! ```text
+//! name: Tiny Stage
+//! -...b-
+//! ..#B..
+//! ..A#..
+//! -a...-
+//! ```
+//!
+//! Each character is one tile:
+//!
+//! | Char | Tile                         |
+//! |------|------------------------------|
+//! | `.`  | Empty                        |
+//! | `#`  | Wall                         |
+//! | `-`  | Not part of the stage        |
+//! | `a`  | Ink belonging to P1          |
+//! | `A`  | Special tile belonging to P1 |
+//! | `b`  | Ink belonging to P2          |
+//! | `B`  | Special tile belonging to P2 |
+//! | `x`  | Conflict tile                |
+//!
+//! Stages normally only contain the first three plus one special tile for each player, which is
+//! where they start, but any board state can be written so that it can be saved and restored.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, line_ending, not_line_ending, space0},
+    combinator::{eof, value},
+    multi::many1,
+    sequence::{delimited, pair, terminated},
+    IResult,
+};
+
+use crate::{
+    board::{Board, Coord, Tile},
+    protocol::PlayerId,
+};
+
+/// An error encountered while parsing a map, with the (1-indexed) position it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// Returns the character for a tile in the map format, where `None` is a tile that isn't part of
+/// the stage.
+pub fn tile_char(tile: Option<Tile>) -> char {
+    match tile {
+        None => '-',
+        Some(Tile::Empty) => '.',
+        Some(Tile::Wall) => '#',
+        Some(Tile::Conflict) => 'x',
+        Some(Tile::Ink { owner, special }) => match (owner, special) {
+            (PlayerId::P1, false) => 'a',
+            (PlayerId::P1, true) => 'A',
+            (PlayerId::P2, false) => 'b',
+            (PlayerId::P2, true) => 'B',
+        },
+    }
+}
+
+fn ink(owner: PlayerId, special: bool) -> Option<Tile> {
+    Some(Tile::Ink { owner, special })
+}
+
+fn header(input: &str) -> IResult<&str, &str> {
+    delimited(pair(tag("name:"), space0), not_line_ending, line_ending)(input)
+}
+
+fn tile(input: &str) -> IResult<&str, Option<Tile>> {
+    alt((
+        value(None, char('-')),
+        value(Some(Tile::Empty), char('.')),
+        value(Some(Tile::Wall), char('#')),
+        value(Some(Tile::Conflict), char('x')),
+        value(ink(PlayerId::P1, false), char('a')),
+        value(ink(PlayerId::P1, true), char('A')),
+        value(ink(PlayerId::P2, false), char('b')),
+        value(ink(PlayerId::P2, true), char('B')),
+    ))(input)
+}
+
+fn row(input: &str) -> IResult<&str, Vec<Option<Tile>>> {
+    terminated(many1(tile), alt((line_ending, eof)))(input)
+}
+
+/// Creates an error pointing at the start of `rest`, which must be a suffix of `input`.
+fn error_at(input: &str, rest: &str, message: impl Into<String>) -> MapError {
+    let consumed = &input[..input.len() - rest.len()];
+    let line = consumed.matches('\n').count() + 1;
+    let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+
+    MapError {
+        line,
+        column: consumed[line_start..].chars().count() + 1,
+        message: message.into(),
+    }
+}
+
+/// Creates an error for a row that failed to parse.
+fn row_error(input: &str, err: nom::Err<nom::error::Error<&str>>) -> MapError {
+    let rest = match err {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+        nom::Err::Incomplete(_) => "",
+    };
+
+    let message = match rest.chars().next() {
+        Some('\r' | '\n') | None => "expected a row of tiles".to_string(),
+        Some(c) => format!("unexpected character {c:?}"),
+    };
+
+    error_at(input, rest, message)
+}
+
+/// Parses a board from the map format described in the [module documentation](self).
+pub fn parse(input: &str) -> Result<Board, MapError> {
+    let (mut rest, name) =
+        header(input).map_err(|_| error_at(input, input, "expected a \"name: <name>\" header"))?;
+
+    let mut rows: Vec<Vec<Option<Tile>>> = Vec::new();
+
+    while !rest.is_empty() {
+        let (next, row) = row(rest).map_err(|e| row_error(input, e))?;
+
+        if let Some(first) = rows.first() {
+            let expected = first.len();
+
+            if row.len() != expected {
+                // Point at the first missing or extra tile
+                let column = row.len().min(expected);
+                let offset = rest
+                    .char_indices()
+                    .nth(column)
+                    .map_or(rest.len(), |(i, _)| i);
+                let at = &rest[offset..];
+
+                return Err(error_at(
+                    input,
+                    at,
+                    format!(
+                        "row has {} tiles but the first row has {expected}",
+                        row.len()
+                    ),
+                ));
+            }
+        }
+
+        rows.push(row);
+        rest = next;
+    }
+
+    if rows.is_empty() {
+        return Err(error_at(input, rest, "map has no rows"));
+    }
+
+    let mut board = HashMap::new();
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if let Some(tile) = tile {
+                board.insert(Coord::new(x as i32, y as i32), *tile);
+            }
+        }
+    }
+
+    Ok(Board::from_tiles(
+        name.trim_end(),
+        rows[0].len() as i32,
+        rows.len() as i32,
+        board,
+    ))
+}
+
+impl FromStr for Board {
+    type Err = MapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+/// Writes the board in the map format described in the [module documentation](self).
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name: {}", self.name())?;
+
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                write!(f, "{}", tile_char(self.get(Coord::new(x, y))))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TINY: &str = "name: Tiny Stage\n-...b-\n..#B..\n..A#..\n-a..x-\n";
+
+    #[test]
+    fn test_parse() {
+        let board = parse(TINY).unwrap();
+
+        assert_eq!(board.name(), "Tiny Stage");
+        assert_eq!((board.width(), board.height()), (6, 4));
+        assert_eq!(board.get(Coord::new(0, 0)), None);
+        assert_eq!(board.get(Coord::new(1, 0)), Some(Tile::Empty));
+        assert_eq!(board.get(Coord::new(2, 1)), Some(Tile::Wall));
+        assert_eq!(board.get(Coord::new(3, 1)), ink(PlayerId::P2, true));
+        assert_eq!(board.get(Coord::new(1, 3)), ink(PlayerId::P1, false));
+        assert_eq!(board.get(Coord::new(4, 3)), Some(Tile::Conflict));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let board = parse(TINY).unwrap();
+        assert_eq!(board.to_string(), TINY);
+        assert_eq!(board.to_string().parse::<Board>().unwrap(), board);
+
+        let mut board = Board::new("Empty", 3, 2);
+        board.set(Coord::new(1, 1), Tile::Wall);
+        assert_eq!(board.to_string().parse::<Board>().unwrap(), board);
+    }
+
+    #[test]
+    fn test_windows_line_endings() {
+        let board = parse(&TINY.replace('\n', "\r\n")).unwrap();
+        assert_eq!(board, parse(TINY).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse("Tiny Stage\n...").unwrap_err(),
+            MapError {
+                line: 1,
+                column: 1,
+                message: "expected a \"name: <name>\" header".to_string()
+            }
+        );
+        assert_eq!(
+            parse("name: Bad\n...\n.?.\n").unwrap_err(),
+            MapError {
+                line: 3,
+                column: 2,
+                message: "unexpected character '?'".to_string()
+            }
+        );
+        assert_eq!(
+            parse("name: Bad\n...\n..\n").unwrap_err(),
+            MapError {
+                line: 3,
+                column: 3,
+                message: "row has 2 tiles but the first row has 3".to_string()
+            }
+        );
+        assert_eq!(parse("name: Bad\n...\n\n...").unwrap_err().line, 3);
+        assert_eq!(parse("name: Bad\n").unwrap_err().message, "map has no rows");
+    }
+}
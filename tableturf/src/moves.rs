@@ -0,0 +1,344 @@
+//! Finding every legal move for a player.
+//!
+//! Checking placements one at a time with [`Board::check_placement`] looks up every square of the
+//! card and all of their neighbours in the board, which adds up quickly when trying every card in
+//! every rotation at every position. [`MoveFinder`] works out once which tiles the player could
+//! ink and which of them are next to the player's ink, so that checking a placement only takes a
+//! few array lookups.
+
+use crate::{
+    board::{Board, Coord, Rotation, Tile},
+    cards::{Card, Footprint},
+    game::PlayerMove,
+    protocol::PlayerId,
+};
+
+/// The tile is empty, so a card can be placed on it.
+const EMPTY: u8 = 1 << 0;
+/// The tile is empty or normal ink, so a special attack can be placed on it.
+const OVERWRITABLE: u8 = 1 << 1;
+/// The tile is next to one of the player's tiles.
+const NEAR_INK: u8 = 1 << 2;
+/// The tile is next to one of the player's special tiles.
+const NEAR_SPECIAL: u8 = 1 << 3;
+
+/// Finds the legal placements of cards for one player on one board.
+#[derive(Debug, Clone)]
+pub struct MoveFinder {
+    player: PlayerId,
+    width: i32,
+    height: i32,
+    /// Flags for each tile of the board's bounding rectangle, row by row.
+    tiles: Vec<u8>,
+}
+
+impl MoveFinder {
+    pub fn new(board: &Board, player: PlayerId) -> Self {
+        let (width, height) = (board.width(), board.height());
+        let mut tiles = vec![0; (width * height).max(0) as usize];
+        let index = |c: Coord| {
+            ((0..width).contains(&c.x) && (0..height).contains(&c.y))
+                .then(|| (c.y * width + c.x) as usize)
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let coord = Coord::new(x, y);
+                let tile = board.get(coord);
+
+                tiles[(y * width + x) as usize] |= match tile {
+                    Some(Tile::Empty) => EMPTY | OVERWRITABLE,
+                    Some(Tile::Ink { special: false, .. }) => OVERWRITABLE,
+                    _ => 0,
+                };
+
+                if let Some(Tile::Ink { owner, special }) = tile {
+                    if owner == player {
+                        let near = if special {
+                            NEAR_INK | NEAR_SPECIAL
+                        } else {
+                            NEAR_INK
+                        };
+
+                        for i in coord.neighbours().filter_map(index) {
+                            tiles[i] |= near;
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            player,
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Returns every legal placement of the card, either all normal placements or all special
+    /// attacks. Whether the player can afford a special attack is not checked.
+    ///
+    /// Only the first of any rotations that cover the same squares is included (see
+    /// [`distinct_rotations`]).
+    pub fn placements(&self, card: &Card, special: bool) -> Vec<PlayerMove> {
+        let mut moves = Vec::new();
+        self.search(card, special, |mv| {
+            moves.push(mv);
+            true
+        });
+        moves
+    }
+
+    /// Returns whether the card can be placed anywhere, normally or as a special attack.
+    pub fn can_place(&self, card: &Card, special: bool) -> bool {
+        let mut found = false;
+        self.search(card, special, |_| {
+            found = true;
+            false
+        });
+        found
+    }
+
+    /// Calls `found` with each legal placement of the card until it returns false.
+    fn search(&self, card: &Card, special: bool, mut found: impl FnMut(PlayerMove) -> bool) {
+        let (fits, near) = if special {
+            (OVERWRITABLE, NEAR_SPECIAL)
+        } else {
+            (EMPTY, NEAR_INK)
+        };
+
+        for (rotation, footprint) in distinct_rotations(card) {
+            // Each square's index relative to the top left of the card's bounding box
+            let squares: Vec<usize> = footprint
+                .squares
+                .iter()
+                .map(|(c, _)| (c.y * self.width + c.x) as usize)
+                .collect();
+
+            for y in 0..=self.height - footprint.height {
+                for x in 0..=self.width - footprint.width {
+                    let top_left = (y * self.width + x) as usize;
+                    let mut connected = false;
+
+                    let legal = squares.iter().all(|&i| {
+                        let tile = self.tiles[top_left + i];
+                        connected |= tile & near != 0;
+                        tile & fits != 0
+                    });
+
+                    if legal && connected {
+                        let mv = PlayerMove::Place {
+                            card: card.id(),
+                            rotation,
+                            anchor: Coord::new(x, y) + footprint.pivot,
+                            special,
+                        };
+
+                        if !found(mv) {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the card's footprint in each rotation, leaving out any rotation that covers exactly
+/// the same squares (including special squares) as an earlier one. A placement in a left out
+/// rotation always inks the same tiles as some placement in an included one.
+///
+/// Cards with no squares have no rotations at all.
+pub fn distinct_rotations(card: &Card) -> Vec<(Rotation, &Footprint)> {
+    let mut rotations: Vec<(Rotation, &Footprint)> = Vec::new();
+
+    for rotation in Rotation::ALL {
+        let footprint = card.footprint(rotation);
+
+        if !footprint.squares.is_empty()
+            && rotations
+                .iter()
+                .all(|(_, f)| f.squares != footprint.squares)
+        {
+            rotations.push((rotation, footprint));
+        }
+    }
+
+    rotations
+}
+
+/// Returns every move the player can make with the given hand: passing with any card, or placing
+/// any card anywhere it fits, as a special attack too if they have the points for it.
+pub fn legal_moves(
+    board: &Board,
+    hand: &[Card],
+    player: PlayerId,
+    special_points: u32,
+) -> Vec<PlayerMove> {
+    let finder = MoveFinder::new(board, player);
+    let mut moves = Vec::new();
+
+    for card in hand {
+        moves.push(PlayerMove::Pass { card: card.id() });
+        moves.extend(finder.placements(card, false));
+
+        if card.cost() <= special_points {
+            moves.extend(finder.placements(card, true));
+        }
+    }
+
+    moves
+}
+
+/// Returns whether the player can place any card in their hand, normally or as a special attack
+/// they have the points for. If not, passing is all they can do.
+pub fn can_place_any(board: &Board, hand: &[Card], player: PlayerId, special_points: u32) -> bool {
+    let finder = MoveFinder::new(board, player);
+
+    hand.iter().any(|card| {
+        finder.can_place(card, false)
+            || (card.cost() <= special_points && finder.can_place(card, true))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{
+        cards::{test_card, CardCatalog},
+        stages::StageCatalog,
+    };
+
+    /// The first standard stage with some ink around both starting positions.
+    fn board() -> Board {
+        let stage = StageCatalog::standard().get(0).unwrap().clone();
+        let mut board = stage.board().clone();
+
+        for player in [PlayerId::P1, PlayerId::P2] {
+            let start = stage.start(player);
+            for (i, coord) in start.neighbours().enumerate() {
+                if matches!(board.get(coord), Some(Tile::Empty)) {
+                    board.set(
+                        coord,
+                        Tile::Ink {
+                            owner: player,
+                            special: i == 0,
+                        },
+                    );
+                }
+            }
+        }
+
+        board
+    }
+
+    /// The tiles a placement inks, which is what matters when comparing placements in different
+    /// rotations.
+    fn inked(card: &Card, mv: &PlayerMove) -> Vec<(Coord, bool)> {
+        let PlayerMove::Place {
+            rotation, anchor, ..
+        } = *mv
+        else {
+            panic!("not a placement: {mv:?}");
+        };
+
+        let mut tiles: Vec<_> = card
+            .footprint(rotation)
+            .offsets()
+            .map(|(offset, special)| (anchor + offset, special))
+            .collect();
+        tiles.sort();
+        tiles
+    }
+
+    #[test]
+    fn test_placements_match_check() {
+        let board = board();
+        let cards = CardCatalog::standard();
+
+        for card in cards.cards().step_by(3) {
+            for special in [false, true] {
+                let finder = MoveFinder::new(&board, PlayerId::P1);
+                let found = finder.placements(card, special);
+
+                // Every placement in every rotation, checked the slow way
+                let mut expected = BTreeSet::new();
+                for rotation in Rotation::ALL {
+                    for y in -4..board.height() + 4 {
+                        for x in -4..board.width() + 4 {
+                            let anchor = Coord::new(x, y);
+                            let legal = if special {
+                                board.check_special_placement(card, rotation, anchor, PlayerId::P1)
+                            } else {
+                                board.check_placement(card, rotation, anchor, PlayerId::P1)
+                            };
+
+                            if legal.is_ok() {
+                                let mv = PlayerMove::Place {
+                                    card: card.id(),
+                                    rotation,
+                                    anchor,
+                                    special,
+                                };
+                                expected.insert(inked(card, &mv));
+                            }
+                        }
+                    }
+                }
+
+                let inked: BTreeSet<_> = found.iter().map(|mv| inked(card, mv)).collect();
+                assert_eq!(inked.len(), found.len(), "duplicate placements");
+                assert_eq!(inked, expected, "card {}, special {special}", card.id());
+                assert_eq!(finder.can_place(card, special), !found.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_distinct_rotations() {
+        let rotations = |rows: &[&str]| distinct_rotations(&test_card(rows)).len();
+
+        assert_eq!(rotations(&["##", "##"]), 1);
+        assert_eq!(rotations(&["###"]), 2);
+        assert_eq!(rotations(&["#.", "##"]), 4);
+        assert_eq!(rotations(&[".#.", "###", ".#."]), 1);
+        // The special square breaks the symmetry
+        assert_eq!(rotations(&["#*", "##"]), 4);
+        assert_eq!(rotations(&[]), 0);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let board = board();
+        let cards = CardCatalog::standard();
+        let hand: Vec<_> = cards.cards().take(4).cloned().collect();
+
+        let moves = legal_moves(&board, &hand, PlayerId::P2, 0);
+        assert_eq!(
+            moves
+                .iter()
+                .filter(|mv| matches!(mv, PlayerMove::Pass { .. }))
+                .count(),
+            4
+        );
+        assert!(moves
+            .iter()
+            .all(|mv| !matches!(mv, PlayerMove::Place { special: true, .. })));
+        assert!(can_place_any(&board, &hand, PlayerId::P2, 0));
+
+        let with_points = legal_moves(&board, &hand, PlayerId::P2, 100);
+        assert!(with_points.len() > moves.len());
+
+        // Nobody can place anything on a board with no ink
+        let empty = Board::new("Empty", 10, 10);
+        assert!(!can_place_any(&empty, &hand, PlayerId::P1, 100));
+        assert_eq!(legal_moves(&empty, &hand, PlayerId::P1, 100).len(), 4);
+    }
+}
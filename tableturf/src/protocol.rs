@@ -1,7 +1,15 @@
-use crate::cards::Deck;
+use crate::{
+    cards::{CardID, Deck, DeckError},
+    game::{ActionError, PlayerMove, TurnOutcome},
+    stages::StageID,
+};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+/// The version of the protocol and rules, which must be bumped whenever a change means that old
+/// clients or replays no longer work.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum PlayerId {
@@ -9,13 +17,50 @@ pub enum PlayerId {
     P2 = 1,
 }
 
+impl PlayerId {
+    pub fn opponent(self) -> PlayerId {
+        match self {
+            PlayerId::P1 => PlayerId::P2,
+            PlayerId::P2 => PlayerId::P1,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
-    HelloClient,
-    /// Response to [`ClientMessage::Pong`]
+    /// Response to [`ClientMessage::HelloServer`]. The token can be used to rejoin a match if the
+    /// connection drops, see [`ClientMessage::Resume`].
+    HelloClient { token: SessionToken },
+    /// Response to [`ClientMessage::Ping`]
     Pong { number: usize },
+    /// Sent in reply to a line that isn't a valid client message, with a description of what was
+    /// wrong with it. The line is otherwise ignored.
+    InvalidMessage { error: String },
     WaitForOpponent,
+    /// Response to [`ClientMessage::CancelFindGame`] once the client has left the matchmaking
+    /// queue. If a match was already found, this isn't sent and the match goes ahead.
+    FindGameCancelled,
+    /// Response to [`ClientMessage::CreateRoom`]. The host's friend can join with the code, and
+    /// the match starts with [`ServerMessage::MatchFound`] once they do.
+    RoomCreated { code: RoomCode },
+    /// Response to [`ClientMessage::JoinRoom`] if there is no room with that code.
+    RoomNotFound,
+    /// Response to [`ClientMessage::LeaveRoom`] once the room has been closed. If someone already
+    /// joined, this isn't sent and the match goes ahead.
+    RoomClosed,
+    /// Response to [`ClientMessage::ListMatches`].
+    MatchList { matches: Vec<MatchSummary> },
+    /// Response to [`ClientMessage::Spectate`] with the public state of the match. From then on
+    /// the spectator is sent the same public messages as the players, such as
+    /// [`ServerMessage::MovesRevealed`] and [`ServerMessage::GameOver`], along with the public
+    /// versions of the players' own messages, such as [`ServerMessage::PlayerMoved`].
+    Spectating { snapshot: SpectatorSnapshot },
+    /// Response to [`ClientMessage::Spectate`] if there is no match with that ID.
+    MatchNotFound,
+    /// The spectated match has ended (or the client sent [`ClientMessage::StopSpectating`]), and
+    /// the client is back in the lobby.
+    SpectatingEnded,
     MatchFound {
         opp_info: PublicPlayerInfo,
         player_id: PlayerId,
@@ -23,9 +68,94 @@ pub enum ServerMessage {
     /// Sent to a client if their opponent disconnects midgame. Depending on the stage of the game
     /// this might count as a win or a draw.
     OpponentDisconnected,
+    /// Sent to a client if their opponent's connection drops midgame. The match is paused for up
+    /// to `timeout` seconds while they try to reconnect, after which the opponent is considered
+    /// disconnected.
+    OpponentReconnecting { timeout: u32 },
+    /// The opponent has reconnected and the match continues.
+    OpponentReconnected,
+    /// Sent to spectators when a player's connection drops midgame. The match is paused for up to
+    /// `timeout` seconds while they try to reconnect.
+    PlayerReconnecting { player: PlayerId, timeout: u32 },
+    /// Sent to spectators when a player has reconnected and the match continues.
+    PlayerReconnected { player: PlayerId },
+    /// Sent to spectators when a player didn't reconnect in time, which ends the match.
+    PlayerDisconnected { player: PlayerId },
+    /// Response to [`ClientMessage::Resume`] with the current state of the match.
+    Resumed { snapshot: MatchSnapshot },
+    /// Response to [`ClientMessage::Resume`] if there is no match to rejoin, for example because
+    /// it has already ended or the player is still connected to it. The server closes the
+    /// connection after sending this.
+    ResumeFailed,
+    /// Sent at the start of every turn (and of the deck and redraw decisions). The client has
+    /// `timeout` seconds to act before the server acts for it. A player who doesn't choose a deck
+    /// in time is sent [`ServerMessage::TimedOut`] and the match is called off.
     StartWithTimeout {
         timeout: u32,
     },
+    /// Response to [`ClientMessage::ChosenDeck`] if the deck is not allowed. The client should
+    /// choose a different deck.
+    InvalidDeck { error: DeckError },
+    /// Both players have chosen their decks and the match is starting on the given stage.
+    ///
+    /// Decks are public, so everyone is told every card in both decks (in the order they were
+    /// chosen), though not the order they will be drawn in.
+    MatchStarted {
+        stage: StageID,
+        decks: [Vec<CardID>; 2],
+    },
+    /// The player's opening hand. The client should reply with [`ClientMessage::Redraw`].
+    OpeningHand { hand: Vec<CardID> },
+    /// The player's new hand after choosing to redraw.
+    HandRedrawn { hand: Vec<CardID> },
+    /// A new turn has started, and the client should send a [`ClientMessage::Move`].
+    TurnStarted { turn: u32 },
+    /// Response to [`ClientMessage::Move`] if the move is allowed. It is locked in and can't be
+    /// changed.
+    MoveAccepted,
+    /// Response to [`ClientMessage::Move`] (or [`ClientMessage::Redraw`]) if it is not allowed.
+    MoveRejected { error: ActionError },
+    /// The opponent has locked in their move for this turn.
+    OpponentMoved,
+    /// Sent to spectators when a player has locked in their move for this turn (or decided
+    /// whether to redraw).
+    PlayerMoved { player: PlayerId },
+    /// Both players have moved, so their moves are shown to everyone.
+    MovesRevealed {
+        turn: u32,
+        moves: [PlayerMove; 2],
+    },
+    /// The result of playing both moves.
+    TurnResult {
+        turn: u32,
+        outcome: TurnOutcome,
+    },
+    /// The player drew a card at the end of the turn.
+    CardDrawn { card: CardID },
+    /// Sent every now and then during a match. `turn` is the number of seconds the player has left
+    /// to act this turn and `total` is what is left of their clock for the whole match.
+    TimeRemaining {
+        turn: u32,
+        total: u32,
+    },
+    /// Sent to spectators at the start of every turn and then every now and then, with the same
+    /// times as [`ServerMessage::TimeRemaining`] for both players.
+    TimesRemaining {
+        turn: [u32; 2],
+        total: [u32; 2],
+    },
+    /// The player ran out of time, so the server played a move for them (passing with the first
+    /// card in their hand, or keeping their opening hand). If they were choosing a deck, the
+    /// match is called off instead and both players go back to the lobby.
+    TimedOut { player: PlayerId },
+    /// The player gave up or was forfeited by the server. This is followed by
+    /// [`ServerMessage::GameOver`].
+    Forfeited { player: PlayerId },
+    /// The last turn has been played. `winner` is None if the match was a draw.
+    GameOver {
+        scores: [u32; 2],
+        winner: Option<PlayerId>,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
@@ -33,10 +163,84 @@ pub struct PublicPlayerInfo {
     pub name: String,
 }
 
+/// A secret identifying a client's session, used to rejoin a match after reconnecting.
+pub type SessionToken = String;
+
+/// A short code that identifies a private room, see [`ClientMessage::CreateRoom`].
+pub type RoomCode = String;
+
+/// Everything a client needs to pick up a match where it left off.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct MatchSnapshot {
+    pub player_id: PlayerId,
+    pub opp_info: PublicPlayerInfo,
+    /// Whether the player has already chosen a valid deck.
+    pub deck_chosen: bool,
+    /// The state of the game, or None if the players are still choosing their decks.
+    pub game: Option<GameSnapshot>,
+}
+
+/// A server-generated ID for a match, used to spectate it.
+pub type MatchId = u64;
+
+/// A match in progress that can be spectated.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct MatchSummary {
+    pub id: MatchId,
+    pub players: [PublicPlayerInfo; 2],
+}
+
+/// Everything a spectator needs to start watching a match. None of the players' hidden
+/// information is included.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SpectatorSnapshot {
+    pub players: [PublicPlayerInfo; 2],
+    /// The state of the game, or None if the players are still choosing their decks.
+    pub game: Option<PublicGameSnapshot>,
+}
+
+/// The state of a game in progress that everyone can see.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PublicGameSnapshot {
+    pub stage: StageID,
+    /// The current board, in the map format (see [`crate::map`]).
+    pub board: String,
+    pub turn: u32,
+    /// Whether both players have decided whether to redraw, so turns are being played.
+    pub started: bool,
+    pub scores: [u32; 2],
+    pub special_points: [u32; 2],
+    /// Whether each player has already acted this turn (or decided whether to redraw).
+    pub acted: [bool; 2],
+    /// The number of seconds each player has left to act this turn.
+    pub timeouts: [u32; 2],
+}
+
+/// The state of a game in progress as seen by one of the players.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct GameSnapshot {
+    pub stage: StageID,
+    /// The current board, in the map format (see [`crate::map`]).
+    pub board: String,
+    pub turn: u32,
+    /// Whether both players have decided whether to redraw, so turns are being played.
+    pub started: bool,
+    pub hand: Vec<CardID>,
+    pub draw_pile_len: usize,
+    pub special_points: [u32; 2],
+    /// Whether each player has already acted this turn (or decided whether to redraw).
+    pub acted: [bool; 2],
+    /// The number of seconds the player has left to act this turn.
+    pub timeout: u32,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
     HelloServer { info: PublicPlayerInfo },
+    /// Sent instead of [`ClientMessage::HelloServer`] by a client that lost its connection during
+    /// a match, to rejoin it using the token from [`ServerMessage::HelloClient`].
+    Resume { token: SessionToken },
     /// Sent to the server every now and then to check if the server is still alive
     /// A client disconnect is detectable by the server but a server crash is undetectable by the
     /// client, hence why this is necessary.
@@ -44,20 +248,181 @@ pub enum ClientMessage {
     /// know that e.g. we are not getting a response meant for a different user.
     Ping { number: usize },
     FindGame,
+    /// Stops looking for a match after sending [`ClientMessage::FindGame`].
+    CancelFindGame,
+    /// Opens a private room that only players who know its code can join, instead of playing
+    /// against whoever is in the matchmaking queue.
+    CreateRoom,
+    /// Joins the private room with the given code. Codes are not case sensitive.
+    JoinRoom { code: RoomCode },
+    /// Closes the room opened with [`ClientMessage::CreateRoom`] before anyone joins it.
+    LeaveRoom,
+    /// Asks for the matches that can be spectated.
+    ListMatches,
+    /// Starts watching a match from [`ServerMessage::MatchList`].
+    Spectate { id: MatchId },
+    StopSpectating,
     Ready,
     ChosenDeck { deck: Deck },
+    /// Whether to redraw the opening hand. This must be sent exactly once, before the first turn.
+    Redraw { redraw: bool },
+    /// The move to lock in for the current turn.
+    Move { mv: PlayerMove },
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        board::{Coord, PlacementError, Rotation, Tile},
+        turn::TileChange,
+    };
 
     #[test]
     fn test_server_protocol_ser() {
-        let hello = ServerMessage::HelloClient;
+        let hello = ServerMessage::HelloClient {
+            token: "abc123".to_string(),
+        };
         let json = serde_json::to_string(&hello).unwrap();
 
-        assert_eq!(json, r#"{"type":"hello_client"}"#.to_string());
+        assert_eq!(
+            json,
+            r#"{"type":"hello_client","token":"abc123"}"#.to_string()
+        );
+    }
+
+    #[test]
+    fn test_resume_protocol() {
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"resume","token":"abc123"}"#)
+                .unwrap(),
+            ClientMessage::Resume {
+                token: "abc123".to_string()
+            },
+        );
+
+        let msg = ServerMessage::Resumed {
+            snapshot: MatchSnapshot {
+                player_id: PlayerId::P2,
+                opp_info: PublicPlayerInfo {
+                    name: "villuna".to_string(),
+                },
+                deck_chosen: true,
+                game: Some(GameSnapshot {
+                    stage: 3,
+                    board: "name: Tiny\nA.b\n".to_string(),
+                    turn: 5,
+                    started: true,
+                    hand: vec![1, 2, 3, 4],
+                    draw_pile_len: 7,
+                    special_points: [2, 0],
+                    acted: [true, false],
+                    timeout: 31,
+                }),
+            },
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_spectator_protocol() {
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"spectate","id":42}"#).unwrap(),
+            ClientMessage::Spectate { id: 42 },
+        );
+
+        let players = [
+            PublicPlayerInfo {
+                name: "villuna".to_string(),
+            },
+            PublicPlayerInfo {
+                name: "callie".to_string(),
+            },
+        ];
+
+        let list = ServerMessage::MatchList {
+            matches: vec![MatchSummary {
+                id: 42,
+                players: players.clone(),
+            }],
+        };
+        assert_eq!(
+            serde_json::to_string(&list).unwrap(),
+            r#"{"type":"match_list","matches":[{"id":42,"players":[{"name":"villuna"},{"name":"callie"}]}]}"#
+        );
+
+        let msg = ServerMessage::Spectating {
+            snapshot: SpectatorSnapshot {
+                players,
+                game: Some(PublicGameSnapshot {
+                    stage: 0,
+                    board: "name: Tiny\nA.b\n".to_string(),
+                    turn: 2,
+                    started: true,
+                    scores: [1, 1],
+                    special_points: [0, 0],
+                    acted: [false, true],
+                    timeouts: [41, 0],
+                }),
+            },
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), msg);
+
+        let msg = ServerMessage::PlayerMoved {
+            player: PlayerId::P2,
+        };
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"type":"player_moved","player":1}"#
+        );
+
+        let msg = ServerMessage::PlayerReconnecting {
+            player: PlayerId::P1,
+            timeout: 60,
+        };
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"type":"player_reconnecting","player":0,"timeout":60}"#
+        );
+
+        let msg = ServerMessage::TimesRemaining {
+            turn: [41, 0],
+            total: [300, 280],
+        };
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"type":"times_remaining","turn":[41,0],"total":[300,280]}"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_deck_ser() {
+        let msg = ServerMessage::InvalidDeck {
+            error: DeckError::Duplicate(3),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"invalid_deck","error":{"type":"duplicate","card":3}}"#
+        );
+    }
+
+    #[test]
+    fn test_match_started_ser() {
+        let msg = ServerMessage::MatchStarted {
+            stage: 2,
+            decks: [vec![1, 2, 3], vec![3, 4, 5]],
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"match_started","stage":2,"decks":[[1,2,3],[3,4,5]]}"#
+        );
+        assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), msg);
     }
 
     #[test]
@@ -72,6 +437,141 @@ mod test {
         assert_eq!(json, r#"{"type":"hello_server","info":{"name":"villuna"}}"#);
     }
 
+    #[test]
+    fn test_game_protocol_ser() {
+        let msg = ServerMessage::MovesRevealed {
+            turn: 3,
+            moves: [
+                PlayerMove::Pass { card: 4 },
+                PlayerMove::Place {
+                    card: 9,
+                    rotation: Rotation::Right,
+                    anchor: Coord::new(2, 5),
+                    special: false,
+                },
+            ],
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"moves_revealed","turn":3,"moves":[{"type":"pass","card":4},{"type":"place","card":9,"rotation":1,"anchor":{"x":2,"y":5},"special":false}]}"#
+        );
+
+        let msg = ServerMessage::MoveRejected {
+            error: ActionError::InvalidPlacement(PlacementError::Wall(Coord::new(1, 2))),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"move_rejected","error":{"type":"invalid_placement","details":{"type":"wall","x":1,"y":2}}}"#
+        );
+
+        let msg = ServerMessage::MoveRejected {
+            error: ActionError::CardNotInHand(12),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"move_rejected","error":{"type":"card_not_in_hand","details":12}}"#
+        );
+        assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_game_protocol_round_trip() {
+        let messages = [
+            ServerMessage::OpeningHand {
+                hand: vec![1, 2, 3, 4],
+            },
+            ServerMessage::HandRedrawn {
+                hand: vec![5, 6, 7, 8],
+            },
+            ServerMessage::TurnStarted { turn: 1 },
+            ServerMessage::MoveAccepted,
+            ServerMessage::MoveRejected {
+                error: ActionError::CardNotInHand(12),
+            },
+            ServerMessage::MoveRejected {
+                error: ActionError::InvalidPlacement(PlacementError::NotEnoughSpecial {
+                    cost: 3,
+                    points: 1,
+                }),
+            },
+            ServerMessage::OpponentMoved,
+            ServerMessage::TurnResult {
+                turn: 1,
+                outcome: TurnOutcome {
+                    diff: vec![TileChange {
+                        coord: Coord::new(4, 4),
+                        old: Tile::Empty,
+                        new: Tile::Ink {
+                            owner: PlayerId::P2,
+                            special: true,
+                        },
+                    }],
+                    activated: vec![Coord::new(0, 0)],
+                    special_points: [1, 0],
+                },
+            },
+            ServerMessage::CardDrawn { card: 15 },
+            ServerMessage::TimeRemaining {
+                turn: 42,
+                total: 300,
+            },
+            ServerMessage::TimedOut {
+                player: PlayerId::P2,
+            },
+            ServerMessage::Forfeited {
+                player: PlayerId::P1,
+            },
+            ServerMessage::GameOver {
+                scores: [40, 38],
+                winner: Some(PlayerId::P1),
+            },
+        ];
+
+        for msg in messages {
+            let json = serde_json::to_string(&msg).unwrap();
+            assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_game_client_protocol_de() {
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"redraw","redraw":true}"#).unwrap(),
+            ClientMessage::Redraw { redraw: true },
+        );
+
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(
+                r#"{"type":"move","mv":{"type":"place","card":2,"rotation":3,"anchor":{"x":-1,"y":0},"special":true}}"#
+            )
+            .unwrap(),
+            ClientMessage::Move {
+                mv: PlayerMove::Place {
+                    card: 2,
+                    rotation: Rotation::Left,
+                    anchor: Coord::new(-1, 0),
+                    special: true,
+                }
+            },
+        );
+
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(
+                r#"{"type":"move","mv":{"type":"pass","card":7}}"#
+            )
+            .unwrap(),
+            ClientMessage::Move {
+                mv: PlayerMove::Pass { card: 7 }
+            },
+        );
+    }
+
     #[test]
     fn test_client_protocol_de() {
         let hello = ClientMessage::HelloServer {
@@ -95,5 +595,18 @@ mod test {
             .unwrap(),
             hello,
         );
+
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"cancel_find_game"}"#).unwrap(),
+            ClientMessage::CancelFindGame,
+        );
+
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"join_room","code":"XK4P9Q"}"#)
+                .unwrap(),
+            ClientMessage::JoinRoom {
+                code: "XK4P9Q".to_string()
+            },
+        );
     }
 }
@@ -0,0 +1,342 @@
+//! Recordings of matches that can be stepped through with the rules engine.
+//!
+//! Since all of the randomness in a match comes from its seed (see [`crate::rng`]), a replay only
+//! needs to store how the match was set up and the actions that were applied to it. Replaying
+//! those actions on a new [`GameState`] reconstructs every board state of the match exactly.
+//!
+//! A replay file is made of JSON values, one per line. The first line is the [`ReplayHeader`] and
+//! every line after it is an [`Action`], in the order they were applied. For example:
+//!
+//! ```text
+//! {"protocol":1,"seed":42,"stage":0,"board":"name: Tiny\n-a.\n.b-\n","decks":[[1,2,...],[3,4,...]],"players":["villuna","callie"]}
+//! {"type":"redraw","player":0,"redraw":false}
+//! {"type":"redraw","player":1,"redraw":true}
+//! {"type":"play","player":1,"mv":{"type":"pass","card":4}}
+//! ```
+//!
+//! Because each action is a line of its own, a replay can be written as the match is played and
+//! still be read if the match never finished. Blank lines are ignored.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cards::{Card, CardCatalog, CardID},
+    game::{Action, ActionError, Event, GameState},
+    map::{self, MapError},
+    protocol::PROTOCOL_VERSION,
+    stages::StageID,
+};
+
+/// How a match was set up.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ReplayHeader {
+    /// The [`PROTOCOL_VERSION`] of the server that recorded the match.
+    pub protocol: u32,
+    pub seed: u64,
+    pub stage: StageID,
+    /// The board the match started on, in the map format (see [`crate::map`]), so that the
+    /// replay still works if the stages change.
+    pub board: String,
+    /// Both players' decks, in the order they were chosen (before they are shuffled).
+    pub decks: [Vec<CardID>; 2],
+    /// The players' names.
+    pub players: [String; 2],
+}
+
+impl ReplayHeader {
+    /// Returns the header as a line of a replay file, including the newline.
+    pub fn to_line(&self) -> String {
+        // Serializing a struct of plain data to JSON can't fail
+        serde_json::to_string(self).unwrap() + "\n"
+    }
+}
+
+/// Returns the action as a line of a replay file, including the newline.
+pub fn action_line(action: &Action) -> String {
+    serde_json::to_string(action).unwrap() + "\n"
+}
+
+/// The reason a replay could not be loaded or played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The file is empty.
+    Empty,
+    /// A line of the file isn't valid. Lines are numbered from 1.
+    Parse { line: usize, message: String },
+    /// The replay was recorded with a different version of the protocol.
+    Version(u32),
+    /// The starting board isn't a valid map.
+    Map(MapError),
+    /// One of the decks has a card that isn't in the card list.
+    UnknownCard(CardID),
+    /// The rules engine rejected one of the actions. Actions are numbered from 0.
+    Action { index: usize, error: ActionError },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Empty => write!(f, "replay is empty"),
+            ReplayError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ReplayError::Version(version) => write!(
+                f,
+                "replay was recorded with protocol version {version}, but this is version {PROTOCOL_VERSION}"
+            ),
+            ReplayError::Map(e) => write!(f, "invalid board: {e}"),
+            ReplayError::UnknownCard(id) => write!(f, "card {id} does not exist"),
+            ReplayError::Action { index, error } => write!(f, "action {index} is invalid: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<MapError> for ReplayError {
+    fn from(e: MapError) -> Self {
+        ReplayError::Map(e)
+    }
+}
+
+/// A recorded match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub actions: Vec<Action>,
+}
+
+impl Replay {
+    /// Parses a replay file. See the [module documentation](self) for the format.
+    pub fn parse(input: &str) -> Result<Self, ReplayError> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (line, header) = lines.next().ok_or(ReplayError::Empty)?;
+        let header: ReplayHeader = parse_line(line, header)?;
+
+        if header.protocol != PROTOCOL_VERSION {
+            return Err(ReplayError::Version(header.protocol));
+        }
+
+        let actions = lines
+            .map(|(line, action)| parse_line(line, action))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { header, actions })
+    }
+
+    /// Sets up the match as it was at the start, ready to step through.
+    pub fn start(&self, cards: &CardCatalog) -> Result<ReplayPlayer<'_>, ReplayError> {
+        let board = map::parse(&self.header.board)?;
+        let deck = |ids: &[CardID]| -> Result<Vec<Card>, ReplayError> {
+            ids.iter()
+                .map(|&id| cards.get(id).cloned().ok_or(ReplayError::UnknownCard(id)))
+                .collect()
+        };
+        let decks = [deck(&self.header.decks[0])?, deck(&self.header.decks[1])?];
+
+        Ok(ReplayPlayer {
+            actions: &self.actions,
+            game: GameState::new(board, decks, self.header.seed),
+            next: 0,
+        })
+    }
+}
+
+fn parse_line<'a, T: Deserialize<'a>>(line: usize, input: &'a str) -> Result<T, ReplayError> {
+    serde_json::from_str(input).map_err(|e| ReplayError::Parse {
+        line,
+        message: e.to_string(),
+    })
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Replay::parse(s)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header.to_line())?;
+
+        for action in &self.actions {
+            write!(f, "{}", action_line(action))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Steps through a replay one action at a time.
+#[derive(Debug, Clone)]
+pub struct ReplayPlayer<'a> {
+    actions: &'a [Action],
+    game: GameState,
+    next: usize,
+}
+
+impl ReplayPlayer<'_> {
+    /// The state of the match after the actions that have been applied so far.
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    /// The number of actions that have been applied so far.
+    pub fn position(&self) -> usize {
+        self.next
+    }
+
+    /// Returns whether every action in the replay has been applied.
+    pub fn is_done(&self) -> bool {
+        self.next >= self.actions.len()
+    }
+
+    /// Applies the next action, returning it along with what happened. Returns None once every
+    /// action has been applied.
+    pub fn step(&mut self) -> Option<Result<(Action, Vec<Event>), ReplayError>> {
+        let action = *self.actions.get(self.next)?;
+        let index = self.next;
+        self.next += 1;
+
+        Some(
+            self.game
+                .apply(action)
+                .map(|events| (action, events))
+                .map_err(|error| ReplayError::Action { index, error }),
+        )
+    }
+
+    /// Applies every remaining action, returning the final state of the match.
+    pub fn finish(mut self) -> Result<GameState, ReplayError> {
+        while let Some(result) = self.step() {
+            result?;
+        }
+
+        Ok(self.game)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{game::PlayerMove, protocol::PlayerId, stages::StageCatalog};
+
+    /// Plays a match where both players pass with the first card in their hand every turn,
+    /// recording it as it goes.
+    fn record() -> (Replay, GameState) {
+        let cards = CardCatalog::standard();
+        let stage = StageCatalog::standard().get(0).unwrap().clone();
+        let decks: [Vec<CardID>; 2] = [(1..16).collect(), (10..25).collect()];
+
+        let header = ReplayHeader {
+            protocol: PROTOCOL_VERSION,
+            seed: 1234,
+            stage: 0,
+            board: stage.board().to_string(),
+            decks: decks.clone(),
+            players: ["villuna".to_string(), "callie".to_string()],
+        };
+
+        let deck = |ids: &[CardID]| {
+            ids.iter()
+                .map(|&id| cards.get(id).unwrap().clone())
+                .collect()
+        };
+        let mut game = GameState::new(
+            stage.board().clone(),
+            [deck(&decks[0]), deck(&decks[1])],
+            1234,
+        );
+        let mut actions = vec![
+            Action::Redraw {
+                player: PlayerId::P1,
+                redraw: false,
+            },
+            Action::Redraw {
+                player: PlayerId::P2,
+                redraw: true,
+            },
+        ];
+
+        for &action in &actions {
+            game.apply(action).unwrap();
+        }
+
+        while !game.is_finished() {
+            for player in [PlayerId::P1, PlayerId::P2] {
+                let card = game.hand(player)[0].id();
+                let action = Action::Play {
+                    player,
+                    mv: PlayerMove::Pass { card },
+                };
+                game.apply(action).unwrap();
+                actions.push(action);
+            }
+        }
+
+        (Replay { header, actions }, game)
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        let (replay, _) = record();
+        let file = replay.to_string();
+
+        assert_eq!(file.lines().count(), replay.actions.len() + 1);
+        assert!(file
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with(r#"{"type":"redraw","player":0"#));
+        assert_eq!(file.parse::<Replay>().unwrap(), replay);
+    }
+
+    #[test]
+    fn test_replay_reconstructs_match() {
+        let (replay, game) = record();
+        let cards = CardCatalog::standard();
+        let mut player = replay.start(&cards).unwrap();
+
+        assert_eq!(
+            player.game().board(),
+            &map::parse(&replay.header.board).unwrap()
+        );
+
+        let (action, _) = player.step().unwrap().unwrap();
+        assert_eq!(action, replay.actions[0]);
+        assert_eq!(player.position(), 1);
+        assert!(!player.is_done());
+
+        let replayed = player.finish().unwrap();
+        assert!(replayed.is_finished());
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.scores(), game.scores());
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let (mut replay, _) = record();
+
+        assert_eq!(Replay::parse("\n\n"), Err(ReplayError::Empty));
+        assert!(matches!(
+            Replay::parse(&(replay.header.to_line() + "{}\n")),
+            Err(ReplayError::Parse { line: 2, .. })
+        ));
+
+        let mut old = replay.header.clone();
+        old.protocol = 0;
+        assert_eq!(Replay::parse(&old.to_line()), Err(ReplayError::Version(0)));
+
+        // Playing a card twice
+        replay.actions.insert(3, replay.actions[2]);
+        let result = replay.start(&CardCatalog::standard()).unwrap().finish();
+        assert!(matches!(result, Err(ReplayError::Action { index: 3, .. })));
+    }
+}
@@ -0,0 +1,107 @@
+//! A small seedable random number generator.
+//!
+//! All of the randomness in a match comes from a single [`Rng`] owned by the
+//! [`GameState`](crate::game::GameState), so a match can be replayed exactly from its seed and the
+//! actions that were applied to it. The algorithm is SplitMix64, which is implemented here rather
+//! than taken from a crate so that the sequence for a given seed can never change underneath us.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`. `n` must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "can't pick a number below 0");
+
+        // Reject the top end of the range so that every result is equally likely
+        let n = n as u64;
+        let zone = u64::MAX - u64::MAX % n;
+
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % n) as usize;
+            }
+        }
+    }
+
+    /// Shuffles the items in place, with every order equally likely.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Returns a seed that is different every time, for starting matches that don't need to be
+/// reproduced ahead of time.
+pub fn random_seed() -> u64 {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+
+    // RandomState is seeded by the OS, so this is unpredictable even if the clock isn't
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(time);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sequence_is_fixed() {
+        // Reference values for SplitMix64 with a seed of 0
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut items: Vec<_> = (0..15).collect();
+        Rng::new(42).shuffle(&mut items);
+
+        let mut again: Vec<_> = (0..15).collect();
+        Rng::new(42).shuffle(&mut again);
+        assert_eq!(items, again);
+
+        let mut sorted = items.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..15).collect::<Vec<_>>());
+        assert_ne!(items, sorted);
+    }
+
+    #[test]
+    fn test_below() {
+        let mut rng = Rng::new(7);
+        for n in 1..50 {
+            assert!(rng.below(n) < n);
+        }
+    }
+}
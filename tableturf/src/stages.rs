@@ -0,0 +1,231 @@
+//! The stages that matches can be played on.
+
+use std::fmt;
+
+use crate::{
+    board::{Board, Coord, Tile},
+    map::{self, MapError},
+    protocol::PlayerId,
+};
+
+pub type StageID = usize;
+
+/// The standard stages, embedded in map format (see [`crate::map`]) so that every binary agrees
+/// on them. A stage's ID is its position in this list.
+const STANDARD_STAGES: &[&str] = &[
+    include_str!("../data/stages/01_main_street.txt"),
+    include_str!("../data/stages/02_thunder_point.txt"),
+    include_str!("../data/stages/03_x_marks_the_garden.txt"),
+    include_str!("../data/stages/04_square_squared.txt"),
+    include_str!("../data/stages/05_lakefront_property.txt"),
+    include_str!("../data/stages/06_double_gemini.txt"),
+    include_str!("../data/stages/07_river_drift.txt"),
+    include_str!("../data/stages/08_box_seats.txt"),
+];
+
+/// A board that a match can be started on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    id: StageID,
+    board: Board,
+    /// The starting special tile of each player.
+    starts: [Coord; 2],
+}
+
+impl Stage {
+    pub fn id(&self) -> StageID {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        self.board.name()
+    }
+
+    /// The board as it is at the start of a match.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the position of the player's starting special tile.
+    pub fn start(&self, player: PlayerId) -> Coord {
+        self.starts[player as usize]
+    }
+}
+
+/// An error encountered while loading a stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageError {
+    Map(MapError),
+    /// The stage does not have exactly one starting special tile for the player.
+    StartingTiles {
+        player: PlayerId,
+        count: usize,
+    },
+    /// The stage contains tiles other than walls, empty tiles and starting special tiles.
+    UnexpectedTile(Coord),
+    DuplicateName(String),
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StageError::Map(e) => write!(f, "{e}"),
+            StageError::StartingTiles { player, count } => write!(
+                f,
+                "stage has {count} starting tiles for {player:?}, but it should have 1"
+            ),
+            StageError::UnexpectedTile(c) => {
+                write!(f, "stage has an inked tile at ({}, {})", c.x, c.y)
+            }
+            StageError::DuplicateName(name) => {
+                write!(f, "more than one stage is called {name:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StageError {}
+
+impl From<MapError> for StageError {
+    fn from(e: MapError) -> Self {
+        StageError::Map(e)
+    }
+}
+
+/// A list of stages that can be looked up by ID or name.
+#[derive(Debug, Clone, Default)]
+pub struct StageCatalog {
+    stages: Vec<Stage>,
+}
+
+impl StageCatalog {
+    /// Returns the standard stages that ship with the game.
+    pub fn standard() -> Self {
+        Self::from_maps(STANDARD_STAGES.iter().copied())
+            .expect("the standard stages should be valid")
+    }
+
+    /// Creates a catalog from stages in the map format. Each stage is given an ID based on its
+    /// position in the list.
+    ///
+    /// Stages must have exactly one special tile for each player, which is where they start, and
+    /// no other inked tiles.
+    pub fn from_maps<'a>(maps: impl IntoIterator<Item = &'a str>) -> Result<Self, StageError> {
+        let mut stages: Vec<Stage> = Vec::new();
+
+        for (id, map) in maps.into_iter().enumerate() {
+            let board = map::parse(map)?;
+
+            if stages.iter().any(|s| s.name() == board.name()) {
+                return Err(StageError::DuplicateName(board.name().to_string()));
+            }
+
+            let starts = [start(&board, PlayerId::P1)?, start(&board, PlayerId::P2)?];
+
+            for y in 0..board.height() {
+                for x in 0..board.width() {
+                    let coord = Coord::new(x, y);
+
+                    if matches!(board.get(coord), Some(Tile::Ink { .. } | Tile::Conflict))
+                        && !starts.contains(&coord)
+                    {
+                        return Err(StageError::UnexpectedTile(coord));
+                    }
+                }
+            }
+
+            stages.push(Stage { id, board, starts });
+        }
+
+        Ok(Self { stages })
+    }
+
+    pub fn get(&self, id: StageID) -> Option<&Stage> {
+        self.stages.get(id)
+    }
+
+    /// Looks up a stage by name, ignoring case.
+    pub fn by_name(&self, name: &str) -> Option<&Stage> {
+        self.stages
+            .iter()
+            .find(|s| s.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns every stage, ordered by ID.
+    pub fn stages(&self) -> impl Iterator<Item = &Stage> {
+        self.stages.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+/// Finds the player's starting special tile on the board.
+fn start(board: &Board, player: PlayerId) -> Result<Coord, StageError> {
+    let tiles: Vec<_> = board
+        .special_tiles()
+        .filter(|&(_, owner)| owner == player)
+        .map(|(coord, _)| coord)
+        .collect();
+
+    match tiles[..] {
+        [coord] => Ok(coord),
+        _ => Err(StageError::StartingTiles {
+            player,
+            count: tiles.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_standard_stages() {
+        let stages = StageCatalog::standard();
+        assert_eq!(stages.len(), STANDARD_STAGES.len());
+
+        let main_street = stages.by_name("main street").unwrap();
+        assert_eq!(main_street.id(), 0);
+        assert_eq!(main_street.name(), "Main Street");
+        assert_eq!(
+            (main_street.board().width(), main_street.board().height()),
+            (9, 26)
+        );
+        assert_eq!(main_street.start(PlayerId::P1), Coord::new(4, 21));
+        assert_eq!(main_street.start(PlayerId::P2), Coord::new(4, 4));
+
+        assert_eq!(stages.get(7).map(Stage::name), Some("Box Seats"));
+        assert!(stages.get(STANDARD_STAGES.len()).is_none());
+        assert!(stages.by_name("Not a stage").is_none());
+    }
+
+    #[test]
+    fn test_invalid_stages() {
+        assert_eq!(
+            StageCatalog::from_maps(["name: A\n..A..\n.....\n"]).unwrap_err(),
+            StageError::StartingTiles {
+                player: PlayerId::P2,
+                count: 0
+            }
+        );
+        assert_eq!(
+            StageCatalog::from_maps(["name: A\n..A..\n.bB..\n"]).unwrap_err(),
+            StageError::UnexpectedTile(Coord::new(1, 1))
+        );
+        assert_eq!(
+            StageCatalog::from_maps(["name: A\nA...B\n", "name: A\nB...A\n"]).unwrap_err(),
+            StageError::DuplicateName("A".to_string())
+        );
+        assert!(matches!(
+            StageCatalog::from_maps(["A...B\n"]),
+            Err(StageError::Map(_))
+        ));
+    }
+}
@@ -0,0 +1,367 @@
+//! Simultaneous resolution of both players' moves at the end of a turn.
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, Coord, PlacementError, Rotation, Tile},
+    cards::Card,
+    protocol::PlayerId,
+};
+
+/// A card placed on the board by a player.
+#[derive(Debug, Copy, Clone)]
+pub struct Placement<'a> {
+    pub card: &'a Card,
+    pub rotation: Rotation,
+    pub anchor: Coord,
+    /// Whether the card is being played as a special attack.
+    pub special: bool,
+}
+
+/// The move a player has locked in for a turn.
+#[derive(Debug, Copy, Clone)]
+pub enum Move<'a> {
+    /// The player discards a card instead of placing it.
+    Pass,
+    Place(Placement<'a>),
+}
+
+/// A single tile that was changed by resolving a turn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileChange {
+    pub coord: Coord,
+    pub old: Tile,
+    pub new: Tile,
+}
+
+/// The outcome of resolving a turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnResult {
+    /// The board after both moves have been applied.
+    pub board: Board,
+    /// Every tile that changed, ordered by coordinate.
+    pub diff: Vec<TileChange>,
+}
+
+/// Returned by [`resolve_turn`] if one of the moves was not legal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidMove {
+    pub player: PlayerId,
+    pub error: PlacementError,
+}
+
+impl fmt::Display for InvalidMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid move by {:?}: {}", self.player, self.error)
+    }
+}
+
+impl std::error::Error for InvalidMove {}
+
+/// Resolves both players' moves at once, returning the new board.
+///
+/// Both moves are checked against the board as it was at the start of the turn. Where the two
+/// cards overlap, the following rules decide who gets the square:
+///
+/// 1. A special square beats a normal square.
+/// 2. Otherwise, the card with more squares is placed first, so the smaller card wins.
+/// 3. If both cards are the same size, the square becomes a [`Tile::Conflict`].
+///
+/// Special attacks overwrite any normal ink underneath them. Whether the player can afford a
+/// special attack is not checked here, see [`crate::game::GameState`].
+pub fn resolve_turn(board: &Board, p1: &Move, p2: &Move) -> Result<TurnResult, InvalidMove> {
+    let squares1 = squares(board, p1, PlayerId::P1)?;
+    let squares2 = squares(board, p2, PlayerId::P2)?;
+
+    let mut placed = BTreeMap::new();
+
+    for (&coord, &special) in &squares1 {
+        placed.insert(coord, (Some(PlayerId::P1), special));
+    }
+
+    for (&coord, &special2) in &squares2 {
+        let square = match squares1.get(&coord) {
+            None => (Some(PlayerId::P2), special2),
+            Some(&special1) if special1 && !special2 => (Some(PlayerId::P1), special1),
+            Some(&special1) if !special1 && special2 => (Some(PlayerId::P2), special2),
+            Some(&special1) => match squares1.len().cmp(&squares2.len()) {
+                Ordering::Less => (Some(PlayerId::P1), special1),
+                Ordering::Greater => (Some(PlayerId::P2), special2),
+                Ordering::Equal => (None, false),
+            },
+        };
+
+        placed.insert(coord, square);
+    }
+
+    let mut new_board = board.clone();
+    let mut diff = Vec::new();
+
+    for (coord, (owner, special)) in placed {
+        let new = match owner {
+            Some(owner) => Tile::Ink { owner, special },
+            None => Tile::Conflict,
+        };
+        // Squares were already checked to be on the board
+        let old = board.get(coord).unwrap_or(Tile::Empty);
+
+        if old != new {
+            new_board.set(coord, new);
+            diff.push(TileChange { coord, old, new });
+        }
+    }
+
+    Ok(TurnResult {
+        board: new_board,
+        diff,
+    })
+}
+
+/// Checks a move is legal and returns the board coordinates of its squares and whether they are
+/// special.
+fn squares(
+    board: &Board,
+    mv: &Move,
+    player: PlayerId,
+) -> Result<BTreeMap<Coord, bool>, InvalidMove> {
+    match mv {
+        Move::Pass => Ok(BTreeMap::new()),
+        Move::Place(p) => {
+            let result = if p.special {
+                board.check_special_placement(p.card, p.rotation, p.anchor, player)
+            } else {
+                board.check_placement(p.card, p.rotation, p.anchor, player)
+            };
+
+            result.map_err(|error| InvalidMove { player, error })?;
+
+            Ok(p.card
+                .footprint(p.rotation)
+                .offsets()
+                .map(|(offset, special)| (p.anchor + offset, special))
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cards::test_card as card;
+
+    /// A 7x3 board with P1 on the left and P2 on the right, so both players can reach the middle
+    /// column.
+    fn board() -> Board {
+        let mut board = Board::new("Test board", 7, 3);
+        for y in 0..3 {
+            board.set(Coord::new(0, y), ink(PlayerId::P1, false));
+            board.set(Coord::new(6, y), ink(PlayerId::P2, false));
+        }
+        board
+    }
+
+    /// Places the card so that the top left of its bounding box is at the given position.
+    fn place(card: &Card, x: i32, y: i32) -> Move<'_> {
+        Move::Place(Placement {
+            card,
+            rotation: Rotation::Up,
+            anchor: Coord::new(x, y) + card.footprint(Rotation::Up).pivot,
+            special: false,
+        })
+    }
+
+    fn ink(owner: PlayerId, special: bool) -> Tile {
+        Tile::Ink { owner, special }
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        let c1 = card(&["##"]);
+        let c2 = card(&["*#"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 0), &place(&c2, 4, 2)).unwrap();
+
+        assert_eq!(
+            result.board.get(Coord::new(1, 0)),
+            Some(ink(PlayerId::P1, false))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(2, 0)),
+            Some(ink(PlayerId::P1, false))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(4, 2)),
+            Some(ink(PlayerId::P2, true))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(5, 2)),
+            Some(ink(PlayerId::P2, false))
+        );
+        assert_eq!(result.diff.len(), 4);
+        assert_eq!(
+            result.diff[0],
+            TileChange {
+                coord: Coord::new(1, 0),
+                old: Tile::Empty,
+                new: ink(PlayerId::P1, false)
+            }
+        );
+    }
+
+    #[test]
+    fn test_pass() {
+        let c1 = card(&["##"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 0), &Move::Pass).unwrap();
+        assert_eq!(result.diff.len(), 2);
+
+        let result = resolve_turn(&board(), &Move::Pass, &Move::Pass).unwrap();
+        assert_eq!(result.board, board());
+        assert!(result.diff.is_empty());
+    }
+
+    #[test]
+    fn test_same_size_conflict() {
+        let c1 = card(&["###"]);
+        let c2 = card(&["###"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 1), &place(&c2, 3, 1)).unwrap();
+
+        assert_eq!(
+            result.board.get(Coord::new(2, 1)),
+            Some(ink(PlayerId::P1, false))
+        );
+        assert_eq!(result.board.get(Coord::new(3, 1)), Some(Tile::Conflict));
+        assert_eq!(
+            result.board.get(Coord::new(4, 1)),
+            Some(ink(PlayerId::P2, false))
+        );
+    }
+
+    #[test]
+    fn test_same_size_both_special_conflict() {
+        let c1 = card(&["##*"]);
+        let c2 = card(&["*##"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 1), &place(&c2, 3, 1)).unwrap();
+
+        assert_eq!(result.board.get(Coord::new(3, 1)), Some(Tile::Conflict));
+    }
+
+    #[test]
+    fn test_special_beats_normal() {
+        // Same size
+        let c1 = card(&["##*"]);
+        let c2 = card(&["###"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 1), &place(&c2, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P1, true))
+        );
+
+        let c3 = card(&["*##"]);
+        let result = resolve_turn(&board(), &place(&c2, 1, 1), &place(&c3, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P2, true))
+        );
+
+        // The special square wins even if its card is the bigger one
+        let c1 = card(&["##*", "###"]);
+        let c2 = card(&["###"]);
+        let result = resolve_turn(&board(), &place(&c1, 1, 1), &place(&c2, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P1, true))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(3, 2)),
+            Some(ink(PlayerId::P1, false))
+        );
+    }
+
+    #[test]
+    fn test_smaller_card_wins() {
+        let small = card(&["###"]);
+        let big = card(&["###", "###"]);
+
+        let result = resolve_turn(&board(), &place(&big, 1, 1), &place(&small, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P2, false))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(3, 2)),
+            Some(ink(PlayerId::P1, false))
+        );
+
+        let result = resolve_turn(&board(), &place(&small, 1, 1), &place(&big, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P1, false))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(4, 1)),
+            Some(ink(PlayerId::P2, false))
+        );
+    }
+
+    #[test]
+    fn test_smaller_card_wins_both_special() {
+        let small = card(&["#*#"]);
+        let big = card(&["###*", "####"]);
+
+        let result = resolve_turn(&board(), &place(&big, 1, 1), &place(&small, 3, 1)).unwrap();
+        assert_eq!(
+            result.board.get(Coord::new(3, 1)),
+            Some(ink(PlayerId::P2, false))
+        );
+        assert_eq!(
+            result.board.get(Coord::new(4, 1)),
+            Some(ink(PlayerId::P2, true))
+        );
+    }
+
+    #[test]
+    fn test_invalid_move() {
+        let c = card(&["##"]);
+
+        assert_eq!(
+            resolve_turn(&board(), &place(&c, 3, 1), &Move::Pass),
+            Err(InvalidMove {
+                player: PlayerId::P1,
+                error: PlacementError::NotConnected
+            })
+        );
+        assert_eq!(
+            resolve_turn(&board(), &Move::Pass, &place(&c, 5, 1)),
+            Err(InvalidMove {
+                player: PlayerId::P2,
+                error: PlacementError::Occupied(Coord::new(6, 1))
+            })
+        );
+    }
+
+    #[test]
+    fn test_special_attack_overwrites_ink() {
+        let mut board = board();
+        board.set(Coord::new(0, 1), ink(PlayerId::P1, true));
+        board.set(Coord::new(1, 1), ink(PlayerId::P2, false));
+
+        let c = card(&["##"]);
+        let attack = Move::Place(Placement {
+            card: &c,
+            rotation: Rotation::Up,
+            anchor: Coord::new(1, 1),
+            special: true,
+        });
+        let result = resolve_turn(&board, &attack, &Move::Pass).unwrap();
+
+        assert_eq!(
+            result.diff[0],
+            TileChange {
+                coord: Coord::new(1, 1),
+                old: ink(PlayerId::P2, false),
+                new: ink(PlayerId::P1, false)
+            }
+        );
+    }
+}